@@ -11,6 +11,16 @@ use std::fs;
 
 include!("src/cli.rs");
 
+// Share templates are validated by the command line parser
+#[allow(dead_code)]
+#[path = "src/template.rs"]
+mod template;
+
+// And so are the kinds of generated secrets
+#[allow(dead_code)]
+#[path = "src/generator.rs"]
+mod generator;
+
 fn main() {
     gen_completions();
     expose_git_describe();
@@ -29,9 +39,9 @@ fn gen_completions() {
     }
 
     let mut app = build_cli();
-    app.gen_completions("rustysecrets", Shell::Bash, &path);
-    app.gen_completions("rustysecrets", Shell::Zsh, &path);
-    app.gen_completions("rustysecrets", Shell::Fish, &path);
+    app.gen_completions("rustysecrets", Shell::Bash, path);
+    app.gen_completions("rustysecrets", Shell::Zsh, path);
+    app.gen_completions("rustysecrets", Shell::Fish, path);
     // app.gen_completions("rustysecrets", Shell::PowerShell, &outdir);
 }
 
@@ -0,0 +1,250 @@
+//! ASCII armor for shares, which survives being pasted in emails and chats.
+//!
+//! Like OpenPGP armor, the share data is encoded in base64 wrapped at 64 columns, preceded
+//! by `Key: Value` headers and followed by a CRC24 checksum. As with mnemonics, the checksum
+//! covers the threshold and the identifier of the share followed by its data, so that the
+//! `Index` and `Threshold` headers cannot be changed either:
+//!
+//! ```text
+//! -----BEGIN RUSTYSECRETS SHARE-----
+//! Index: 1
+//! Threshold: 2
+//! Mode: wrapped
+//! Mime-Type: text/plain
+//!
+//! Cp8Bi+xgL66RCykEuLBN9lqZo+In3/sa3L64l8o8L8z3PLKKrNviC5A4h74MiOp/
+//! +iNi7u/9ECvoSTUNK7auZQTWr+jtuRz84YARJOnGu05u6e8w6AS6ViZafTMSh7YA
+//! zkTVFYUqS8mDxLIOAvhXytnxw6D6e4jaZGORkCOir7pQg16Fm1F9InoCWalfO4xN
+//! GuvS5cwB4I/YPnec7UdYS5by
+//! =lHmK
+//! -----END RUSTYSECRETS SHARE-----
+//! ```
+//!
+//! Any text around the armored blocks is ignored.
+
+use base64;
+
+use errors::*;
+
+const BEGIN: &str = "-----BEGIN RUSTYSECRETS SHARE-----";
+const END: &str = "-----END RUSTYSECRETS SHARE-----";
+const LINE_LEN: usize = 64;
+
+const CRC24_INIT: u32 = 0x00B7_04CE;
+const CRC24_POLY: u32 = 0x0186_4CFB;
+
+/// The identifier, threshold, other headers and data of an armored block.
+pub struct Block {
+    pub id: u8,
+    pub threshold: u8,
+    pub headers: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+/// Whether the content holds armored blocks.
+pub fn is_armored(content: &str) -> bool {
+    content.lines().any(|line| line.trim() == BEGIN)
+}
+
+/// Armors the data along with the identifier and threshold of the share, and the given headers.
+pub fn encode(id: u8, threshold: u8, headers: &[(String, String)], data: &[u8]) -> String {
+    let mut armor = format!("{}\nIndex: {}\nThreshold: {}\n", BEGIN, id, threshold);
+
+    for (key, value) in headers {
+        armor.push_str(&format!("{}: {}\n", key, value));
+    }
+    armor.push('\n');
+
+    let body = base64::encode(data);
+    for line in body.as_bytes().chunks(LINE_LEN) {
+        armor.push_str(&String::from_utf8_lossy(line));
+        armor.push('\n');
+    }
+
+    let crc = crc24(&checked_bytes(id, threshold, data));
+    let crc = [(crc >> 16) as u8, (crc >> 8) as u8, crc as u8];
+    armor.push_str(&format!("={}\n{}\n", base64::encode(&crc), END));
+
+    armor
+}
+
+/// Decodes all the armored blocks found in the content, checking their checksum.
+pub fn decode_all(content: &str) -> Result<Vec<Block>> {
+    let mut blocks = Vec::new();
+    let mut lines = content.lines().map(str::trim);
+
+    while lines.any(|line| line == BEGIN) {
+        let block = lines.by_ref().take_while(|&line| line != END).collect::<Vec<_>>();
+        blocks.push(decode_block(&block)?);
+    }
+
+    Ok(blocks)
+}
+
+fn decode_block(lines: &[&str]) -> Result<Block> {
+    let (mut id, mut threshold, mut headers) = (None, None, Vec::new());
+    let mut lines = lines.iter();
+
+    for line in lines.by_ref().take_while(|line| !line.is_empty()) {
+        let (key, value) = match line.find(':') {
+            Some(colon) => (line[..colon].trim(), line[colon + 1..].trim()),
+            None => bail!(ErrorKind::InvalidArmor(format!("invalid header '{}'", line))),
+        };
+
+        let parse = |value: &str| match value.parse::<u8>() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(ErrorKind::InvalidArmor(format!("invalid {} header '{}'", key, value))),
+        };
+
+        if key.eq_ignore_ascii_case("Index") {
+            id = parse(value)?;
+        } else if key.eq_ignore_ascii_case("Threshold") {
+            threshold = parse(value)?;
+        } else {
+            headers.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    let (id, threshold) = match (id, threshold) {
+        (Some(id), Some(threshold)) => (id, threshold),
+        _ => bail!(ErrorKind::InvalidArmor("missing Index or Threshold header".to_string())),
+    };
+
+    let (checksum, body): (Vec<&str>, Vec<&str>) = lines.partition(|line| line.starts_with('='));
+
+    let checksum = match checksum.as_slice() {
+        [checksum] => base64::decode(&checksum[1..])
+            .ok()
+            .filter(|crc| crc.len() == 3)
+            .map(|crc| crc.iter().fold(0, |crc, &byte| (crc << 8) | u32::from(byte))),
+        [] => bail!(ErrorKind::InvalidArmor("missing checksum".to_string())),
+        _ => None,
+    };
+
+    let checksum = match checksum {
+        Some(checksum) => checksum,
+        None => bail!(ErrorKind::InvalidArmor("invalid checksum".to_string())),
+    };
+
+    let data = match base64::decode(&body.concat()) {
+        Ok(data) => data,
+        Err(_) => bail!(ErrorKind::ArmorChecksumMismatch),
+    };
+
+    if crc24(&checked_bytes(id, threshold, &data)) != checksum {
+        bail!(ErrorKind::ArmorChecksumMismatch);
+    }
+
+    Ok(Block {
+        id,
+        threshold,
+        headers,
+        data,
+    })
+}
+
+/// The bytes covered by the checksum, laid out as in mnemonics.
+fn checked_bytes(id: u8, threshold: u8, data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![threshold, id];
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// The CRC24 checksum of OpenPGP, see RFC 4880 section 6.1.
+fn crc24(data: &[u8]) -> u32 {
+    let crc = data.iter().fold(CRC24_INIT, |crc, &byte| {
+        (0..8).fold(crc ^ (u32::from(byte) << 16), |crc, _| {
+            let crc = crc << 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^ CRC24_POLY
+            } else {
+                crc
+            }
+        })
+    });
+
+    crc & 0x00FF_FFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<(String, String)> {
+        vec![("Mime-Type".to_string(), "text/plain".to_string())]
+    }
+
+    #[test]
+    fn crc24_matches_openpgp() {
+        assert_eq!(crc24(b""), CRC24_INIT);
+        assert_eq!(crc24(b"123456789"), 0x0021_CF02);
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        let blocks = decode_all(&format!("Some text\n{}\nmore text", encode(1, 2, &headers(), &data))).unwrap();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].id, blocks[0].threshold), (1, 2));
+        assert_eq!(blocks[0].headers, headers());
+        assert_eq!(blocks[0].data, data);
+    }
+
+    #[test]
+    fn corrupted_data() {
+        let armor = encode(1, 2, &headers(), b"some share data");
+        let corrupted = armor.replacen("c29tZS", "c29tZT", 1);
+
+        assert_ne!(armor, corrupted);
+        match decode_all(&corrupted) {
+            Err(Error(ErrorKind::ArmorChecksumMismatch, _)) => {}
+            _ => panic!("expected a checksum mismatch"),
+        }
+    }
+
+    #[test]
+    fn tampered_headers() {
+        let armor = encode(1, 2, &headers(), b"some share data");
+
+        for tampered in &[
+            armor.replace("Index: 1", "Index: 3"),
+            armor.replace("Threshold: 2", "Threshold: 1"),
+        ] {
+            match decode_all(tampered) {
+                Err(Error(ErrorKind::ArmorChecksumMismatch, _)) => {}
+                _ => panic!("expected a checksum mismatch"),
+            }
+        }
+
+        match decode_all(&armor.replace("Index: 1", "Index: one")) {
+            Err(Error(ErrorKind::InvalidArmor(_), _)) => {}
+            _ => panic!("expected an invalid header"),
+        }
+    }
+
+    #[test]
+    fn bad_checksums() {
+        let armor = encode(1, 2, &headers(), b"some share data");
+        let checksum = armor.lines().find(|line| line.starts_with('=')).unwrap();
+
+        for (replacement, mismatch) in &[("=AAAA", true), ("=AA", false), ("", false)] {
+            let armor = armor.replace(checksum, replacement);
+            match decode_all(&armor) {
+                Err(Error(ErrorKind::ArmorChecksumMismatch, _)) => assert!(mismatch),
+                Err(Error(ErrorKind::InvalidArmor(_), _)) => assert!(!mismatch),
+                _ => panic!("expected an error for checksum '{}'", replacement),
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_header() {
+        let armor = encode(1, 2, &headers(), b"some share data").replace("Index: 1", "Index 1");
+
+        match decode_all(&armor) {
+            Err(Error(ErrorKind::InvalidArmor(_), _)) => {}
+            _ => panic!("expected an invalid header"),
+        }
+    }
+}
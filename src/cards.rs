@@ -0,0 +1,112 @@
+//! Printable share cards, one A4 page per share, handed out to the custodians at the end of a key ceremony.
+
+use pdf::{self, Font, Page};
+use qr;
+use share::{Format, Share};
+use version;
+
+use errors::*;
+
+const MARGIN: f64 = 50.0;
+const QR_SIZE: f64 = 200.0;
+
+/// Characters per group and groups per line of the text shares.
+const GROUP_LEN: usize = 5;
+const GROUPS_PER_LINE: usize = 8;
+
+/// Room left for the share between the QR code and the signature lines.
+const SHARE_TOP: f64 = 520.0;
+const SHARE_BOTTOM: f64 = 250.0;
+
+const SIGNATURE_FIELDS: [&str; 4] = ["Custodian", "Signature", "Date", "Witness"];
+
+/// What is printed on the card of a share.
+pub struct Card<'a> {
+    pub share: &'a Share,
+    pub label: String,
+    /// Number of shares in the set, or in the group of the share
+    pub count: u8,
+    pub date: String,
+}
+
+/// Lays out the cards as a PDF document.
+pub fn render(cards: &[Card]) -> Result<Vec<u8>> {
+    let pages = cards.iter().map(render_card).collect::<Result<Vec<_>>>()?;
+
+    Ok(pdf::write(&pages))
+}
+
+fn render_card(card: &Card) -> Result<Page> {
+    let mut page = Page::new();
+    let share = card.share;
+    let top = pdf::PAGE_HEIGHT - MARGIN;
+
+    page.text(MARGIN, top - 20.0, Font::Bold, 20.0, &card.label);
+
+    let details = [
+        format!("Share #{}", share.id),
+        format!("{} of {} shares needed", share.threshold, card.count),
+        format!("Set ID: {}", share.set_id().unwrap_or_else(|| "unknown".to_string())),
+        format!("Created: {}", card.date),
+        format!("Format: {}", share.format),
+    ];
+    for (i, detail) in details.iter().enumerate() {
+        page.text(MARGIN, top - 55.0 - 18.0 * i as f64, Font::Regular, 12.0, detail);
+    }
+
+    let code = qr::modules(&share.to_string())?;
+    let module = QR_SIZE / code.width as f64;
+    let (qr_x, qr_y) = (pdf::PAGE_WIDTH - MARGIN - QR_SIZE, top - QR_SIZE);
+    for (x, y) in code.dark() {
+        // Slightly larger modules leave no gap between neighbours
+        page.fill_rect(
+            qr_x + x as f64 * module,
+            qr_y + QR_SIZE - (y + 1) as f64 * module,
+            module + 0.1,
+            module + 0.1,
+        );
+    }
+
+    let lines = share_lines(share);
+    let line_height = ((SHARE_TOP - SHARE_BOTTOM) / lines.len() as f64).min(16.0);
+    for (i, line) in lines.iter().enumerate() {
+        page.text(MARGIN, SHARE_TOP - line_height * i as f64, Font::Mono, line_height * 0.7, line);
+    }
+
+    for (i, field) in SIGNATURE_FIELDS.iter().enumerate() {
+        let y = SHARE_BOTTOM - 50.0 - 40.0 * i as f64;
+        page.text(MARGIN, y, Font::Regular, 12.0, field);
+        page.line(MARGIN + 80.0, y - 2.0, pdf::PAGE_WIDTH - MARGIN, y - 2.0);
+    }
+
+    page.text(
+        MARGIN,
+        MARGIN,
+        Font::Regular,
+        9.0,
+        &format!("Keep this card secret. Printed by rustysecrets {}.", version::get()),
+    );
+
+    Ok(page)
+}
+
+/// The share as it should be typed back: its headers, then its body, in groups of characters or words.
+fn share_lines(share: &Share) -> Vec<String> {
+    let text = share.to_string();
+    let mut lines = text.lines().map(String::from).collect::<Vec<_>>();
+
+    if share.format == Format::Text {
+        lines.pop();
+
+        let chars = share.as_str().chars().collect::<Vec<_>>();
+        for line in chars.chunks(GROUP_LEN * GROUPS_PER_LINE) {
+            let groups = line
+                .chunks(GROUP_LEN)
+                .map(|group| group.iter().collect::<String>())
+                .collect::<Vec<_>>();
+            lines.push(groups.join(" "));
+        }
+    }
+
+    lines
+}
@@ -20,13 +20,13 @@ pub fn build_cli() -> App<'static, 'static> {
                          .help("Enable verbose mode"))
                     .arg(Arg::with_name("k")
                          .short("k")
-                         .required(true)
+                         .required_unless_one(&["group", "policy"])
                          .takes_value(true)
                          .validator(validators::num::strictly_positive)
                          .help("Number of shares necessary to recover the secret"))
                     .arg(Arg::with_name("n")
                          .short("n")
-                         .required(true)
+                         .required_unless_one(&["group", "policy", "weights", "custodians"])
                          .takes_value(true)
                          .validator(validators::num::strictly_positive)
                          .help("Total number of generated shares"))
@@ -58,8 +58,152 @@ pub fn build_cli() -> App<'static, 'static> {
                          .short("s")
                          .long("sign")
                          .help("Sign the shares"))
+                    .arg(Arg::with_name("format")
+                         .short("f")
+                         .long("format")
+                         .takes_value(true)
+                         .value_name("FORMAT")
+                         .possible_values(&["text", "mnemonic", "slip39", "armor"])
+                         .help("How to write the shares, mnemonic shares are sequences of words. Defaults to 'text'"))
+                    .arg(Arg::with_name("qr")
+                         .long("qr")
+                         .takes_value(true)
+                         .value_name("FORMAT")
+                         .possible_values(&["png", "svg"])
+                         .help("Also write each share as a QR code image, to print it"))
+                    .arg(Arg::with_name("qr-only")
+                         .long("qr-only")
+                         .requires("qr")
+                         .help("Only write the QR code images, and not the share files"))
+                    .arg(Arg::with_name("print-pdf")
+                         .long("print-pdf")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .help("Also write a printable card for each share to this PDF file"))
+                    .arg(Arg::with_name("label")
+                         .long("label")
+                         .takes_value(true)
+                         .value_name("LABEL")
+                         .help("Text printed with the shares on QR codes and cards. Defaults to the share file name"))
+                    .arg(Arg::with_name("policy")
+                         .long("policy")
+                         .takes_value(true)
+                         .value_name("POLICY")
+                         .conflicts_with_all(&["k", "n", "group"])
+                         .help("Split the secret between groups of shares, such as '2 of (exec:2/3, eng:3/5)'"))
+                    .arg(Arg::with_name("weights")
+                         .long("weights")
+                         .takes_value(true)
+                         .value_name("HOLDERS")
+                         .conflicts_with_all(&["n", "group", "policy", "qr"])
+                         .help("Give several shares to some holders, in a file named after each holder, such as 'alice=2,bob=1'"))
+                    .arg(Arg::with_name("custodians")
+                         .long("custodians")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .conflicts_with_all(&["n", "group", "policy", "weights"])
+                         .validator(validators::fs::file)
+                         .help("JSON file listing the custodian of each share, and where to write it"))
+                    .arg(Arg::with_name("recipient")
+                         .long("recipient")
+                         .takes_value(true)
+                         .value_name("N=PUBKEY")
+                         .multiple(true)
+                         .number_of_values(1)
+                         .conflicts_with_all(&["policy", "weights", "group", "qr", "print-pdf"])
+                         .help("Encrypt share #N, numbered from 1 as its Index header, to this age public key, such as '1=age1...'"))
+                    .arg(Arg::with_name("recipients-file")
+                         .long("recipients-file")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .conflicts_with_all(&["policy", "weights", "group", "qr", "print-pdf"])
+                         .validator(validators::fs::file)
+                         .help("File listing a N=PUBKEY recipient per line, to encrypt the shares to"))
+                    .arg(Arg::with_name("passphrase-protect")
+                         .long("passphrase-protect")
+                         .conflicts_with_all(&["qr", "print-pdf"])
+                         .help("Protect each share file with a passphrase, asked for on the terminal by default"))
+                    .arg(Arg::with_name("share-passphrases")
+                         .long("share-passphrases")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .requires("passphrase-protect")
+                         .validator(validators::fs::file)
+                         .help("File listing the passphrase of each share file, one per line"))
+                    .arg(Arg::with_name("share-passphrase-env")
+                         .long("share-passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .requires("passphrase-protect")
+                         .conflicts_with("share-passphrases")
+                         .help("Protect every share file with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("group")
+                         .long("group")
+                         .takes_value(true)
+                         .value_name("T/N")
+                         .multiple(true)
+                         .number_of_values(1)
+                         .conflicts_with_all(&["k", "n"])
+                         .requires("group-threshold")
+                         .validator(validators::slip39::group)
+                         .help("Add a group of N SLIP-39 shares, T of which are needed"))
+                    .arg(Arg::with_name("group-threshold")
+                         .long("group-threshold")
+                         .takes_value(true)
+                         .value_name("G")
+                         .requires("group")
+                         .validator(validators::num::strictly_positive)
+                         .help("Number of SLIP-39 groups necessary to recover the secret"))
+                    .arg(Arg::with_name("iteration-exponent")
+                         .long("iteration-exponent")
+                         .takes_value(true)
+                         .value_name("E")
+                         .validator(validators::slip39::iteration_exponent)
+                         .help("Makes the SLIP-39 passphrase encryption 2^E times slower. Defaults to 1"))
+                    .arg(Arg::with_name("passphrase-env")
+                         .long("passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .help("Encrypt the SLIP-39 master secret with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("encrypt")
+                         .short("e")
+                         .long("encrypt")
+                         .takes_value(true)
+                         .value_name("CIPHERTEXT")
+                         .help("Encrypt the secret with a random key to this file, and only split the key"))
+                    .arg(Arg::with_name("secret-env")
+                         .long("secret-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .conflicts_with_all(&["INPUT", "secret-fd", "secret-prompt", "generate"])
+                         .help("Read the secret from this environment variable"))
+                    .arg(Arg::with_name("secret-fd")
+                         .long("secret-fd")
+                         .takes_value(true)
+                         .value_name("FD")
+                         .validator(validators::fs::fd)
+                         .conflicts_with_all(&["INPUT", "secret-prompt", "generate"])
+                         .help("Read the secret from this file descriptor, inherited from the calling program"))
+                    .arg(Arg::with_name("secret-prompt")
+                         .long("secret-prompt")
+                         .conflicts_with_all(&["INPUT", "generate"])
+                         .help("Type the secret on the terminal, without echo, and confirm it"))
+                    .arg(Arg::with_name("generate")
+                         .long("generate")
+                         .takes_value(true)
+                         .value_name("KIND")
+                         .validator(validators::rusty_secrets::generator)
+                         .conflicts_with("INPUT")
+                         .help("Split a random secret of this kind: N or bytes:N, hex:N, base58:N or words:N"))
+                    .arg(Arg::with_name("public-key")
+                         .long("public-key")
+                         .takes_value(true)
+                         .value_name("ALGORITHM")
+                         .possible_values(&["ed25519"])
+                         .requires("generate")
+                         .help("Also print the public key of the generated secret, used as a private key"))
                     .arg(Arg::with_name("INPUT")
-                         .required(true)
+                         .required_unless_one(&["secret-env", "secret-fd", "secret-prompt", "generate"])
                          .validator(validators::fs::file_or_stdin)
                          .help("Path to the file containing the secret to split, or - to read from stdin")))
         .subcommand(SubCommand::with_name("recover")
@@ -71,37 +215,325 @@ pub fn build_cli() -> App<'static, 'static> {
                          .short("v")
                          .long("verbose")
                          .help("Enable verbose mode"))
-                    .arg(Arg::with_name("SHARES")
-                         .required(true)
+                    .arg(Arg::with_name("identity")
+                         .long("identity")
                          .takes_value(true)
+                         .value_name("FILE")
                          .multiple(true)
+                         .number_of_values(1)
+                         .validator(validators::fs::file)
+                         .help("File holding the age secret key to decrypt encrypted shares with"))
+                    .arg(Arg::with_name("share-passphrases")
+                         .long("share-passphrases")
+                         .takes_value(true)
+                         .value_name("FILE")
                          .validator(validators::fs::file)
-                         .help("Paths to shares to recover the secret from"))
+                         .help("File listing the passphrases of the protected shares, one per line, in order"))
+                    .arg(Arg::with_name("share-passphrase-env")
+                         .long("share-passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .conflicts_with("share-passphrases")
+                         .help("Unlock the protected shares with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("SHARES")
+                         .required_unless("interactive")
+                         .takes_value(true)
+                         .multiple(true)
+                         .validator(validators::fs::shares)
+                         .help("Paths to shares, QR codes, directories or patterns of shares to recover the secret from, or - for stdin"))
+                    .arg(Arg::with_name("interactive")
+                         .long("interactive")
+                         .conflicts_with_all(&["SHARES", "find-bad"])
+                         .help("Type the shares one at a time on the terminal, without echo, instead of reading files"))
                     .arg(Arg::with_name("raw")
                          .short("r")
                          .long("raw")
-                         .help("Include this flag if the shares are raw (ie. do not contain a MIME type)"))
+                         .help("Treat the shares as raw (ie. not containing a MIME type), instead of detecting it"))
+                    .arg(Arg::with_name("wrapped")
+                         .short("w")
+                         .long("wrapped")
+                         .conflicts_with("raw")
+                         .help("Treat the shares as wrapped (ie. containing a MIME type), instead of detecting it"))
                     .arg(Arg::with_name("verify")
                          .long("verify")
                          .help("Verify the shares signatures"))
+                    .arg(Arg::with_name("passphrase-env")
+                         .long("passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .help("Decrypt the SLIP-39 master secret with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("ciphertext")
+                         .long("ciphertext")
+                         .value_name("FILE")
+                         .takes_value(true)
+                         .validator(validators::fs::file)
+                         .help("Path to the encrypted secret, if not next to the shares"))
+                    .arg(Arg::with_name("manifest")
+                         .long("manifest")
+                         .value_name("FILE")
+                         .takes_value(true)
+                         .validator(validators::fs::file)
+                         .help("Check that the shares are listed in the manifest written by split before recovering"))
+                    .arg(Arg::with_name("find-bad")
+                         .long("find-bad")
+                         .help("Find the inconsistent shares and recover the secret without them"))
                     .arg(Arg::with_name("FILE")
                          .short("o")
                          .long("output")
                          .takes_value(true)
                          .help("Path to file to output the secret to, prints to stdout if omitted")))
+        .subcommand(SubCommand::with_name("inspect")
+                    .about("Print information about shares, without recovering the secret")
+                    .visible_alias("i")
+                    .setting(AppSettings::ColoredHelp)
+                    .setting(AppSettings::UnifiedHelpMessage)
+                    .arg(Arg::with_name("verbose")
+                         .short("v")
+                         .long("verbose")
+                         .help("Enable verbose mode"))
+                    .arg(Arg::with_name("identity")
+                         .long("identity")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .multiple(true)
+                         .number_of_values(1)
+                         .validator(validators::fs::file)
+                         .help("File holding the age secret key to decrypt encrypted shares with"))
+                    .arg(Arg::with_name("share-passphrases")
+                         .long("share-passphrases")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .validator(validators::fs::file)
+                         .help("File listing the passphrases of the protected shares, one per line, in order"))
+                    .arg(Arg::with_name("share-passphrase-env")
+                         .long("share-passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .conflicts_with("share-passphrases")
+                         .help("Unlock the protected shares with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("SHARES")
+                         .required(true)
+                         .takes_value(true)
+                         .multiple(true)
+                         .validator(validators::fs::file)
+                         .help("Paths to shares to inspect")))
+        .subcommand(SubCommand::with_name("verify")
+                    .about("Check that the shares recover a secret, without outputting it")
+                    .setting(AppSettings::ColoredHelp)
+                    .setting(AppSettings::UnifiedHelpMessage)
+                    .arg(Arg::with_name("verbose")
+                         .short("v")
+                         .long("verbose")
+                         .help("Enable verbose mode"))
+                    .arg(Arg::with_name("identity")
+                         .long("identity")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .multiple(true)
+                         .number_of_values(1)
+                         .validator(validators::fs::file)
+                         .help("File holding the age secret key to decrypt encrypted shares with"))
+                    .arg(Arg::with_name("share-passphrases")
+                         .long("share-passphrases")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .validator(validators::fs::file)
+                         .help("File listing the passphrases of the protected shares, one per line, in order"))
+                    .arg(Arg::with_name("share-passphrase-env")
+                         .long("share-passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .conflicts_with("share-passphrases")
+                         .help("Unlock the protected shares with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("SHARES")
+                         .required(true)
+                         .takes_value(true)
+                         .multiple(true)
+                         .validator(validators::fs::file)
+                         .help("Paths to shares to verify"))
+                    .arg(Arg::with_name("passphrase-env")
+                         .long("passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .help("Decrypt the SLIP-39 master secret with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("ciphertext")
+                         .long("ciphertext")
+                         .value_name("FILE")
+                         .takes_value(true)
+                         .validator(validators::fs::file)
+                         .help("Path to the encrypted secret, if not next to the shares"))
+                    .arg(Arg::with_name("raw")
+                         .short("r")
+                         .long("raw")
+                         .help("Treat the shares as raw (ie. not containing a MIME type), instead of detecting it"))
+                    .arg(Arg::with_name("wrapped")
+                         .short("w")
+                         .long("wrapped")
+                         .conflicts_with("raw")
+                         .help("Treat the shares as wrapped (ie. containing a MIME type), instead of detecting it")))
+        .subcommand(SubCommand::with_name("reshare")
+                    .about("Split the secret held by existing shares into new shares")
+                    .setting(AppSettings::ColoredHelp)
+                    .setting(AppSettings::UnifiedHelpMessage)
+                    .arg(Arg::with_name("verbose")
+                         .short("v")
+                         .long("verbose")
+                         .help("Enable verbose mode"))
+                    .arg(Arg::with_name("k")
+                         .short("k")
+                         .required(true)
+                         .takes_value(true)
+                         .validator(validators::num::strictly_positive)
+                         .help("Number of new shares necessary to recover the secret"))
+                    .arg(Arg::with_name("n")
+                         .short("n")
+                         .required(true)
+                         .takes_value(true)
+                         .validator(validators::num::strictly_positive)
+                         .help("Total number of generated shares"))
+                    .arg(Arg::with_name("share-tmpl")
+                         .short("t")
+                         .long("share-tmpl")
+                         .takes_value(true)
+                         .validator(validators::rusty_secrets::share_tmpl)
+                         .help("Template for the share names. Defaults to 'share_{{num}}'"))
+                    .arg(Arg::with_name("DIR")
+                         .short("o")
+                         .long("output")
+                         .required(true)
+                         .takes_value(true)
+                         .validator(validators::fs::directory)
+                         .help("Path to the directory to output the new shares to"))
+                    .arg(Arg::with_name("sign")
+                         .short("s")
+                         .long("sign")
+                         .help("Sign the new shares"))
+                    .arg(Arg::with_name("identity")
+                         .long("identity")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .multiple(true)
+                         .number_of_values(1)
+                         .validator(validators::fs::file)
+                         .help("File holding the age secret key to decrypt encrypted shares with"))
+                    .arg(Arg::with_name("share-passphrases")
+                         .long("share-passphrases")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .validator(validators::fs::file)
+                         .help("File listing the passphrases of the protected shares, one per line, in order"))
+                    .arg(Arg::with_name("share-passphrase-env")
+                         .long("share-passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .conflicts_with("share-passphrases")
+                         .help("Unlock the protected shares with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("SHARES")
+                         .required(true)
+                         .takes_value(true)
+                         .multiple(true)
+                         .validator(validators::fs::file)
+                         .help("Paths to the existing shares")))
+        .subcommand(SubCommand::with_name("extend")
+                    .about("Generate additional shares for an existing set of shares")
+                    .setting(AppSettings::ColoredHelp)
+                    .setting(AppSettings::UnifiedHelpMessage)
+                    .arg(Arg::with_name("verbose")
+                         .short("v")
+                         .long("verbose")
+                         .help("Enable verbose mode"))
+                    .arg(Arg::with_name("count")
+                         .short("c")
+                         .long("count")
+                         .takes_value(true)
+                         .validator(validators::num::strictly_positive)
+                         .help("Number of shares to generate, after the highest index in use. Defaults to 1"))
+                    .arg(Arg::with_name("index")
+                         .short("i")
+                         .long("index")
+                         .takes_value(true)
+                         .multiple(true)
+                         .number_of_values(1)
+                         .conflicts_with("count")
+                         .validator(validators::num::strictly_positive)
+                         .help("Index of a share to generate, starting from 1"))
+                    .arg(Arg::with_name("share-tmpl")
+                         .short("t")
+                         .long("share-tmpl")
+                         .takes_value(true)
+                         .validator(validators::rusty_secrets::share_tmpl)
+                         .help("Template for the share names. Defaults to 'share_{{num}}'"))
+                    .arg(Arg::with_name("DIR")
+                         .short("o")
+                         .long("output")
+                         .required(true)
+                         .takes_value(true)
+                         .validator(validators::fs::directory)
+                         .help("Path to the directory to output the new shares to"))
+                    .arg(Arg::with_name("identity")
+                         .long("identity")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .multiple(true)
+                         .number_of_values(1)
+                         .validator(validators::fs::file)
+                         .help("File holding the age secret key to decrypt encrypted shares with"))
+                    .arg(Arg::with_name("share-passphrases")
+                         .long("share-passphrases")
+                         .takes_value(true)
+                         .value_name("FILE")
+                         .validator(validators::fs::file)
+                         .help("File listing the passphrases of the protected shares, one per line, in order"))
+                    .arg(Arg::with_name("share-passphrase-env")
+                         .long("share-passphrase-env")
+                         .takes_value(true)
+                         .value_name("VAR")
+                         .conflicts_with("share-passphrases")
+                         .help("Unlock the protected shares with the passphrase held by this environment variable"))
+                    .arg(Arg::with_name("SHARES")
+                         .required(true)
+                         .takes_value(true)
+                         .multiple(true)
+                         .validator(validators::fs::file)
+                         .help("Paths to at least k existing shares")))
 }
 
-#[cfg_attr(feature = "cargo-clippy", allow(needless_pass_by_value))]
 pub mod validators {
 
     pub mod rusty_secrets {
 
         pub fn share_tmpl(value: String) -> Result<(), String> {
-            if !value.contains("{{num}}") {
-                return Err("Invalid share template, must contain '{[num]}'".to_string());
+            value
+                .parse::<::template::Template>()
+                .map(|_| ())
+                .map_err(|e| format!("Invalid share template, {}", e))
+        }
+
+        pub fn generator(value: String) -> Result<(), String> {
+            value
+                .parse::<::generator::Generator>()
+                .map(|_| ())
+                .map_err(|e| format!("Invalid kind of secret, {}", e))
+        }
+
+    }
+
+    pub mod slip39 {
+
+        pub fn group(value: String) -> Result<(), String> {
+            let parts = value.split('/').map(|part| part.parse::<u8>()).collect::<Vec<_>>();
+
+            match parts.as_slice() {
+                [Ok(t), Ok(n)] if 1 <= *t && t <= n && *n <= 16 => Ok(()),
+                _ => Err(format!("{} is not a valid group, expected T/N with 1 <= T <= N <= 16", value)),
             }
+        }
 
-            Ok(())
+        pub fn iteration_exponent(value: String) -> Result<(), String> {
+            match value.parse::<u8>() {
+                Ok(e) if e < 16 => Ok(()),
+                _ => Err(format!("{} is not a valid iteration exponent, expected 0 to 15", value)),
+            }
         }
 
     }
@@ -160,6 +592,25 @@ pub mod validators {
             file(value)
         }
 
+        pub fn fd(value: String) -> Result<(), String> {
+            match value.parse::<i32>() {
+                Ok(fd) if fd > 2 => Ok(()),
+                Ok(_) => Err(format!("{} is a standard stream, use - to read the secret from stdin", value)),
+                Err(_) => Err(format!("{} is not a file descriptor", value)),
+            }
+        }
+
+        pub fn shares(value: String) -> Result<(), String> {
+            let path = Path::new(&value);
+
+            // Patterns are expanded when reading the shares, for shells which do not expand them
+            if value == "-" || path.is_dir() || value.contains(&['*', '?', '['][..]) {
+                return Ok(());
+            }
+
+            file(value)
+        }
+
         pub fn directory(value: String) -> Result<(), String> {
             let path = Path::new(&value);
 
@@ -0,0 +1,177 @@
+use errors::*;
+use gf256::Gf256;
+use manifest::{self, Manifest};
+use share::Share;
+
+use super::recover::{is_grouped, is_slip39, read_shares, recover_secret, Keys};
+use super::split::{share_path, today, write_shares};
+use template::Template;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub fn extend(
+    shares_paths: Vec<&Path>,
+    output_path: &Path,
+    share_tmpl: &Template,
+    indexes: Option<Vec<u8>>,
+    count: u8,
+    keys: &Keys,
+) -> Result<()> {
+    let shares = read_shares(shares_paths, keys)?;
+
+    if is_slip39(&shares) {
+        bail!(ErrorKind::Slip39Unsupported("Extending".to_string()));
+    }
+
+    if is_grouped(&shares) {
+        bail!(ErrorKind::PolicyUnsupported("Extending".to_string()));
+    }
+
+    // Signatures are tied to the original set of shares, and new shares could not be signed
+    if shares.iter().any(|share| share.signed) {
+        bail!(ErrorKind::CannotExtendSignedShares);
+    }
+
+    // Makes RustySecrets validate the shares, and tells us whether they are raw or wrapped
+    let secret = recover_secret(&shares, false, None)?;
+
+    let gf256 = Gf256::new();
+    let threshold = shares[0].threshold;
+    let (basis, others) = shares.split_at(threshold as usize);
+
+    let evaluate_at = |x: u8| {
+        (0..basis[0].data.len())
+            .map(|i| {
+                let points = basis.iter().map(|share| (share.id, share.data[i])).collect::<Vec<_>>();
+                gf256.interpolate_at(&points, x)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for share in others {
+        if evaluate_at(share.id) != share.data {
+            bail!(ErrorKind::InconsistentShare(share.source().to_string()));
+        }
+    }
+
+    let set_id = shares[0].set_id();
+
+    // The new shares are added to the manifest of the set, if it is in the output directory
+    let manifest_path = output_path.join(manifest::FILE_NAME);
+    let manifest = if manifest_path.exists() {
+        let manifest = Manifest::load(&manifest_path)?;
+        if manifest.set_id != set_id {
+            bail!(ErrorKind::ManifestOfAnotherSet(format!("{}", manifest_path.display())));
+        }
+        Some(manifest)
+    } else {
+        None
+    };
+
+    let used = used_indexes(&shares, output_path, &set_id);
+
+    let indexes = match indexes {
+        Some(indexes) => {
+            for (i, index) in indexes.iter().enumerate() {
+                if let Some(source) = used.get(index) {
+                    bail!(ErrorKind::ShareIndexAlreadyUsed(*index, source.clone()));
+                }
+                if indexes[..i].contains(index) {
+                    bail!(ErrorKind::DuplicateShareIndex(*index));
+                }
+            }
+            indexes
+        }
+        None => {
+            let first = used.keys().max().map_or(1, |&max| u16::from(max) + 1);
+            let last = first + u16::from(count) - 1;
+
+            if last > 255 {
+                bail!(ErrorKind::NoFreeShareIndex(count));
+            }
+
+            (first..last + 1).map(|index| index as u8).collect()
+        }
+    };
+
+    debug!("Generating shares #{:?}...", indexes);
+
+    // The set now goes up to the highest index in use
+    let n = used.keys().chain(&indexes).cloned().max().unwrap_or(threshold);
+
+    let date = today();
+    let mut new_shares = Vec::with_capacity(indexes.len());
+
+    for index in indexes {
+        let mut share = Share::from_data(threshold, index, evaluate_at(index), secret.mode)?;
+        share.format = shares[0].format;
+
+        // The new shares belong to the same set as the existing ones
+        if let Some(ref set_id) = set_id {
+            share.set_header("Set-Id", set_id);
+        }
+
+        if let Some(ref ciphertext) = secret.ciphertext {
+            share.set_header("Ciphertext", ciphertext);
+        }
+
+        let path = share_path(output_path, share_tmpl, &date, &share, n);
+
+        if path.exists() {
+            bail!(ErrorKind::ShareFileAlreadyExists(format!("{}", path.display())));
+        }
+
+        new_shares.push(share);
+    }
+
+    write_shares(&new_shares, n, output_path, share_tmpl, &date)?;
+
+    let files = new_shares
+        .iter()
+        .map(|share| share_path(Path::new(""), share_tmpl, &date, share, n))
+        .collect();
+
+    match manifest {
+        Some(mut manifest) => {
+            manifest.n = manifest.n.max(n);
+            manifest.add(&new_shares, files);
+            manifest.write(&manifest_path)?;
+        }
+        None => Manifest::new(&new_shares, threshold, n, Vec::new(), secret.mime_type, files).create(&manifest_path)?,
+    }
+
+    debug!("Wrote manifest to '{}'", manifest_path.display());
+
+    Ok(())
+}
+
+/// Collects the identifiers of the given shares, and of the shares of the same set found in the output directory.
+fn used_indexes(shares: &[Share], output_path: &Path, set_id: &Option<String>) -> HashMap<u8, String> {
+    let mut used = HashMap::new();
+
+    for share in shares {
+        used.insert(share.id, share.source().to_string());
+    }
+
+    let entries = match fs::read_dir(output_path) {
+        Ok(entries) => entries,
+        Err(_) => return used,
+    };
+
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        // Anything which does not look like a share is ignored, and holders may have several shares
+        let shares = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| Share::parse_all(&content).ok())
+            .unwrap_or_default();
+
+        // The shares of other sets may use any index
+        for share in shares.into_iter().filter(|share| share.set_id() == *set_id) {
+            used.entry(share.id).or_insert_with(|| format!("{}", path.display()));
+        }
+    }
+
+    used
+}
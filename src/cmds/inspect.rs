@@ -0,0 +1,35 @@
+use errors::*;
+
+use super::recover::{read_share_file, Keys};
+
+use std::path::Path;
+
+pub fn inspect(shares_paths: Vec<&Path>, keys: &Keys) -> Result<()> {
+    for share_path in shares_paths {
+        // The file of a holder holds several shares
+        for share in read_share_file(share_path, keys)? {
+            let mode = match share.mode()? {
+                Some(mode) => mode.to_string(),
+                None => "unknown".to_string(),
+            };
+
+            println!("{}", share_path.display());
+            println!("    Index:     {}", share.id);
+            println!("    Threshold: {}", share.threshold);
+            if let Some(group) = share.header("Group") {
+                println!("    Group:     {}", group);
+                println!("    Policy:    {}", share.header("Policy").unwrap_or("unknown"));
+            }
+            if let Some(holder) = share.header("Holder") {
+                println!("    Holder:    {}", holder);
+            }
+            println!("    Set ID:    {}", share.set_id().unwrap_or_else(|| "unknown".to_string()));
+            println!("    Mode:      {}", mode);
+            println!("    Format:    {}", share.format);
+            println!("    Signed:    {}", if share.signed { "yes" } else { "no" });
+            println!("    Payload:   {} bytes", share.data.len());
+        }
+    }
+
+    Ok(())
+}
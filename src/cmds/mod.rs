@@ -1,5 +1,13 @@
 mod split;
 mod recover;
+mod inspect;
+mod verify;
+mod reshare;
+mod extend;
 
-pub use self::split::split;
-pub use self::recover::recover;
+pub use self::split::{split, Printouts, Protection, SplitOptions};
+pub use self::recover::{recover, Keys, RecoverOptions};
+pub use self::inspect::inspect;
+pub use self::verify::verify;
+pub use self::reshare::reshare;
+pub use self::extend::extend;
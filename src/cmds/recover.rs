@@ -1,78 +1,963 @@
-use rusty_secrets::{sss, wrapped_secrets};
+use protobuf::{self, Message};
+use rusty_secrets::proto::wrapped::SecretProto;
+use rusty_secrets::sss;
 
+use age::{self, Identity};
+use armor;
+use envelope;
 use errors::*;
+use glob;
+use manifest::{self, Manifest};
+use policy::Policy;
+use protect::{self, Passphrases};
+use progress::Progress;
+use qr;
+use rpassword;
+use quorum::find_quorum;
+use share::{Format, Mode, Share};
+use slip39;
 
-use std::path::Path;
-use std::fs::File;
-use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
 
-pub fn recover(shares_paths: Vec<&Path>, output_path: Option<&Path>, verify_signatures: bool, raw: bool) -> Result<()> {
+/// How to recover the secret, and where to write it.
+pub struct RecoverOptions<'a> {
+    /// The file to write the secret to, instead of stdout
+    pub output_path: Option<&'a Path>,
+    pub verify_signatures: bool,
+    /// Whether the shares are raw or wrapped, detected if not given
+    pub mode: Option<Mode>,
+    /// Whether to recover the secret without the shares which are inconsistent with the others
+    pub find_bad: bool,
+    /// The encrypted secret, if not next to the shares
+    pub ciphertext_path: Option<&'a Path>,
+    /// The passphrase of SLIP-39 master secrets
+    pub passphrase: &'a str,
+    /// The manifest the shares must be listed in
+    pub manifest_path: Option<&'a Path>,
+    pub keys: &'a Keys,
+    /// Whether to ask for the shares on the terminal, instead of reading them from files
+    pub interactive: bool,
+}
+
+pub fn recover(shares_paths: Vec<&Path>, options: RecoverOptions) -> Result<()> {
+    let RecoverOptions {
+        output_path,
+        verify_signatures,
+        mode,
+        find_bad,
+        ciphertext_path,
+        passphrase,
+        manifest_path,
+        keys,
+        interactive,
+    } = options;
+
+    let shares_dirs = parent_dirs(&shares_paths);
+
+    let manifest = match manifest_path {
+        Some(manifest_path) => Some(Manifest::load(manifest_path)?),
+        None => None,
+    };
+
+    let secret = if find_bad {
+        recover_consistent_secret(shares_paths, verify_signatures, mode, manifest.as_ref(), keys)?
+    } else {
+        let shares = if interactive {
+            read_interactive_shares()?
+        } else {
+            read_shares(shares_paths, keys)?
+        };
+
+        if let Some(ref manifest) = manifest {
+            check_manifest(&shares, manifest)?;
+        }
+
+        report_custodians(&shares);
+
+        if is_slip39(&shares) {
+            recover_slip39_secret(&shares, passphrase)?
+        } else if is_grouped(&shares) {
+            recover_policy_secret(&shares, verify_signatures, mode)?
+        } else {
+            recover_secret(&shares, verify_signatures, mode)?
+        }
+    };
+
+    let ciphertext_path = find_ciphertext(&secret, &shares_dirs, ciphertext_path)?;
+
+    match output_path {
+        Some(output_path) => {
+            let written = {
+                let mut output_file = File::create(output_path)
+                    .map(BufWriter::new)
+                    .chain_err(|| ErrorKind::CannotCreateSecretFile(format!("{}", output_path.display())))?;
+
+                match ciphertext_path {
+                    Some(ref ciphertext_path) => decrypt_ciphertext(&secret.data, ciphertext_path, &mut output_file),
+                    None => output_file
+                        .write_all(&secret.data)
+                        .and_then(|_| output_file.flush())
+                        .chain_err(|| ErrorKind::CannotWriteSecretToFile(format!("{}", output_path.display()))),
+                }
+            };
+
+            // Do not leave a truncated secret behind
+            if written.is_err() {
+                let _ = fs::remove_file(output_path);
+            }
+
+            written?;
+        }
+        None => {
+            // See https://github.com/romac/rustysecrets-cli/issues/9
+            // let secret_str = String::from_utf8(secret)
+            //     .chain_err(|| "Could not parse secret as UTF-8, consider outputting it to a file instead")?;
+
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+
+            match ciphertext_path {
+                Some(ref ciphertext_path) => decrypt_ciphertext(&secret.data, ciphertext_path, &mut stdout)?,
+                None => stdout
+                    .write_all(&secret.data)
+                    .chain_err(|| ErrorKind::CannotWriteSecretToStdout)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A recovered secret, along with what is known about it.
+pub(crate) struct Secret {
+    pub data: Vec<u8>,
+    pub mode: Mode,
+    pub mime_type: Option<String>,
+    /// The name of the file holding the encrypted secret, if the shares only hold its key
+    pub ciphertext: Option<String>,
+}
+
+pub(crate) fn recover_secret(shares: &[Share], verify_signatures: bool, mode: Option<Mode>) -> Result<Secret> {
+    let mode = match mode {
+        Some(mode) => Some(mode),
+        None => detect_mode(shares)?,
+    };
+
+    check_share_set(shares)?;
+
+    if shares.iter().any(|share| share.header("Holder").is_some()) {
+        check_votes(shares)?;
+    }
+
+    let ciphertext = ciphertext_name(shares)?;
+
+    let shares = shares
+        .iter()
+        .map(|share| share.as_str().to_string())
+        .collect::<Vec<_>>();
+
+    debug!("Recovering secret... ");
+
+    // Wrapped secrets are raw secrets holding a `SecretProto`,
+    // so we can recover first and unwrap afterwards if needed.
+    let secret = sss::recover_secret(&shares, verify_signatures).chain_err(|| ErrorKind::CannotRecoverSecret)?;
+
+    Ok(Secret {
+        ciphertext,
+        ..unwrap_secret(secret, mode)?
+    })
+}
+
+/// Whether the shares are SLIP-39 shares, which RustySecrets cannot recover.
+pub(crate) fn is_slip39(shares: &[Share]) -> bool {
+    shares.iter().any(|share| share.format == Format::Slip39)
+}
+
+/// Recovers and decrypts the master secret of SLIP-39 shares, which is always raw.
+pub(crate) fn recover_slip39_secret(shares: &[Share], passphrase: &str) -> Result<Secret> {
+    check_share_set(shares)?;
+
+    let mut slip39_shares = Vec::with_capacity(shares.len());
+
+    for share in shares {
+        if share.format != Format::Slip39 {
+            bail!(ErrorKind::InvalidSlip39Shares(format!(
+                "'{}' is not a SLIP-39 share",
+                share.source()
+            )));
+        }
+
+        let words = share.as_str().split(' ').collect::<Vec<_>>();
+        slip39_shares.push(slip39::Share::from_words(&words)?);
+    }
+
+    debug!("Recovering SLIP-39 secret... ");
+
+    let data = slip39::combine(&slip39_shares, passphrase).chain_err(|| ErrorKind::CannotRecoverSecret)?;
+
+    Ok(Secret {
+        data,
+        mode: Mode::Raw,
+        mime_type: None,
+        ciphertext: ciphertext_name(shares)?,
+    })
+}
+
+/// Whether the shares belong to the groups of a policy.
+pub(crate) fn is_grouped(shares: &[Share]) -> bool {
+    shares.iter().any(|share| share.header("Group").is_some())
+}
+
+/// Recovers the share of each group with enough shares, then the secret from the shares of the groups.
+pub(crate) fn recover_policy_secret(shares: &[Share], verify_signatures: bool, mode: Option<Mode>) -> Result<Secret> {
+    check_share_set(shares)?;
+
+    let policy = policy(shares)?;
+
+    for share in shares {
+        match share.header("Group") {
+            Some(group) if policy.group(group).is_some() => {}
+            group => bail!(ErrorKind::UnknownGroup(
+                share.source().to_string(),
+                group.unwrap_or("<none>").to_string()
+            )),
+        }
+    }
+
+    let mut group_shares = Vec::new();
+    let mut short = Vec::new();
+
+    for group in &policy.groups {
+        let members = shares
+            .iter()
+            .filter(|share| share.header("Group") == Some(group.name.as_str()))
+            .map(|share| share.as_str().to_string())
+            .collect::<Vec<_>>();
+
+        if members.len() < usize::from(group.k) {
+            short.push(format!("{} has {} of {} shares", group.name, members.len(), group.k));
+            continue;
+        }
+
+        debug!("Recovering the share of group '{}'...", group.name);
+
+        let group_share = sss::recover_secret(&members, verify_signatures)
+            .chain_err(|| ErrorKind::CannotRecoverSecret)
+            .and_then(|share| String::from_utf8(share).chain_err(|| ErrorKind::CannotRecoverSecret))
+            .and_then(|share| Share::parse(&share).chain_err(|| ErrorKind::CannotRecoverSecret))?;
+
+        group_shares.push(group_share);
+    }
+
+    if group_shares.len() < usize::from(policy.threshold) {
+        bail!(ErrorKind::PolicyNotMet(
+            policy.threshold - group_shares.len() as u8,
+            short
+        ));
+    }
+
+    let mode = match mode {
+        Some(mode) => Some(mode),
+        None => detect_mode(shares)?,
+    };
+
+    Ok(Secret {
+        ciphertext: ciphertext_name(shares)?,
+        ..recover_secret(&group_shares, verify_signatures, mode)?
+    })
+}
+
+/// The policy the shares were split with, which they must all agree on.
+fn policy(shares: &[Share]) -> Result<Policy> {
+    let mut policies = shares.iter().filter_map(|share| share.header("Policy"));
+
+    let first = match policies.next() {
+        Some(first) => first,
+        None => bail!(ErrorKind::InvalidShare("missing Policy header".to_string())),
+    };
+
+    if let Some(other) = policies.find(|&policy| policy != first) {
+        bail!(ErrorKind::InvalidShare(format!(
+            "conflicting policies '{}' and '{}'",
+            first, other
+        )));
+    }
+
+    first.parse()
+}
+
+/// Recovers the secret from the largest set of consistent shares,
+/// reporting the shares which cannot be read or do not belong to it.
+fn recover_consistent_secret(
+    shares_paths: Vec<&Path>,
+    verify_signatures: bool,
+    mode: Option<Mode>,
+    manifest: Option<&Manifest>,
+    keys: &Keys,
+) -> Result<Secret> {
     let mut shares = Vec::with_capacity(shares_paths.len());
 
     for share_path in shares_paths {
-        if !share_path.exists() {
-            bail!(ErrorKind::ShareDoesNotExists(format!(
-                "{}",
-                share_path.display()
-            ),))
+        if is_stdin(share_path) {
+            match read_stdin_shares() {
+                Ok(read) => shares.extend(read),
+                Err(e) => warn!("{}, skipping it", e),
+            }
+            continue;
         }
-        if !share_path.is_file() {
-            bail!(ErrorKind::ShareIsNotAFile(format!(
-                "{}",
-                share_path.display()
-            ),))
+
+        let paths = if is_dir_or_pattern(share_path) {
+            find_share_files(share_path)?
+        } else {
+            vec![share_path.to_path_buf()]
+        };
+
+        for path in paths {
+            match read_share_file(&path, keys) {
+                Ok(read) => shares.extend(read),
+                Err(e) => warn!("{}, skipping it", e),
+            }
         }
+    }
 
-        debug!("Reading share {:?}... ", share_path);
+    if let Some(manifest) = manifest {
+        shares.retain(|share| {
+            let listed = manifest.entry(share).is_some();
+            if !listed {
+                warn!("Share '{}' is not listed in the manifest, skipping it", share.source());
+            }
+            listed
+        });
+    }
 
-        let mut share_file =
-            File::open(share_path).chain_err(|| ErrorKind::CannotOpenShare(format!("{}", share_path.display())))?;
+    if is_slip39(&shares) {
+        bail!(ErrorKind::Slip39Unsupported("Finding bad shares".to_string()));
+    }
 
-        let mut share = String::new();
-        let size = share_file
-            .read_to_string(&mut share)
-            .chain_err(|| ErrorKind::CannotReadShare(format!("{}", share_path.display())))?;
+    if is_grouped(&shares) {
+        bail!(ErrorKind::PolicyUnsupported("Finding bad shares".to_string()));
+    }
 
-        debug!("Read {} bytes.", size);
+    let verify_signatures = verify_signatures || shares.iter().any(|share| share.signed);
 
-        shares.push(share);
+    debug!("Looking for consistent shares... ");
+
+    let quorum = find_quorum(&shares, verify_signatures).chain_err(|| ErrorKind::CannotRecoverSecret)?;
+
+    for &i in &quorum.bad {
+        warn!("Share '{}' is inconsistent with the others", shares[i].source());
     }
 
-    debug!("Recovering secret... ");
+    debug!("Found {} consistent shares", quorum.good.len());
 
-    let secret = if raw {
-        sss::recover_secret(&shares, verify_signatures).chain_err(|| ErrorKind::CannotRecoverSecret)?
-    } else {
-        let mut res =
-            wrapped_secrets::recover_secret(&shares, verify_signatures).chain_err(|| ErrorKind::CannotRecoverSecret)?;
+    let good = quorum
+        .good
+        .iter()
+        .map(|&i| shares[i].clone())
+        .collect::<Vec<_>>();
+
+    report_custodians(&good);
+
+    let mode = match mode {
+        Some(mode) => Some(mode),
+        None => detect_mode(&good)?,
+    };
+
+    let ciphertext = ciphertext_name(&good)?;
+
+    Ok(Secret {
+        ciphertext,
+        ..unwrap_secret(quorum.secret, mode)?
+    })
+}
+
+/// Returns the path of the ciphertext whose key was held by the shares, if any,
+/// looking for it next to the shares and in the current directory.
+pub(crate) fn find_ciphertext(
+    secret: &Secret,
+    shares_dirs: &[PathBuf],
+    ciphertext_path: Option<&Path>,
+) -> Result<Option<PathBuf>> {
+    match (ciphertext_path, secret.ciphertext.as_ref()) {
+        (Some(ciphertext_path), _) => Ok(Some(ciphertext_path.to_path_buf())),
+        (None, Some(name)) => shares_dirs
+            .iter()
+            .map(|dir| dir.join(name))
+            .chain(Some(PathBuf::from(name)))
+            .find(|path| path.is_file())
+            .map(Some)
+            .ok_or_else(|| ErrorKind::CiphertextNotFound(name.clone()).into()),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Streams the decrypted ciphertext to `output`, without holding it in memory.
+pub(crate) fn decrypt_ciphertext<W: Write>(key: &[u8], ciphertext_path: &Path, output: W) -> Result<()> {
+    debug!("Decrypting secret from '{}'...", ciphertext_path.display());
+
+    let ciphertext_file = File::open(ciphertext_path)
+        .chain_err(|| ErrorKind::CannotReadCiphertext(format!("{}", ciphertext_path.display())))?;
+
+    let size = ciphertext_file.metadata().ok().map(|metadata| metadata.len());
+    let mut progress = Progress::new("Decrypted", size);
+
+    envelope::open(key, BufReader::new(ciphertext_file), output, &mut progress)
+        .chain_err(|| ErrorKind::CannotDecryptCiphertext(format!("{}", ciphertext_path.display())))
+}
+
+/// Returns the name of the ciphertext the shares hold the key of, if any.
+fn ciphertext_name(shares: &[Share]) -> Result<Option<String>> {
+    let mut names = shares.iter().filter_map(|share| share.header("Ciphertext"));
+
+    let first = match names.next() {
+        Some(first) => first,
+        None => return Ok(None),
+    };
 
-        debug!("Version: {:?}", res.get_version());
+    if let Some(other) = names.find(|&name| name != first) {
+        bail!(ErrorKind::ConflictingCiphertexts(first.to_string(), other.to_string()));
+    }
+
+    Ok(Some(first.to_string()))
+}
+
+/// Extracts the secret out of a recovered `SecretProto` for wrapped shares,
+/// detecting whether it is one if the mode is unknown.
+fn unwrap_secret(secret: Vec<u8>, mode: Option<Mode>) -> Result<Secret> {
+    let mode = mode.unwrap_or_else(|| {
+        let mode = if is_wrapped(&secret) {
+            Mode::Wrapped
+        } else {
+            Mode::Raw
+        };
+        debug!("Detected {} shares", mode);
+        mode
+    });
+
+    match mode {
+        Mode::Raw => Ok(Secret {
+            data: secret,
+            mode,
+            mime_type: None,
+            ciphertext: None,
+        }),
+        Mode::Wrapped => {
+            let mut res =
+                protobuf::parse_from_bytes::<SecretProto>(&secret).chain_err(|| ErrorKind::CannotRecoverSecret)?;
 
-        if res.get_mime_type() != "" {
-            debug!("MIME-Type: {}", res.get_mime_type());
+            debug!("Version: {:?}", res.get_version());
+
+            let mime_type = if res.get_mime_type() != "" {
+                debug!("MIME-Type: {}", res.get_mime_type());
+                Some(res.take_mime_type())
+            } else {
+                None
+            };
+
+            Ok(Secret {
+                data: res.take_secret(),
+                mode,
+                mime_type,
+                ciphertext: None,
+            })
         }
+    }
+}
 
-        res.take_secret()
+/// Returns the mode of the shares according to their headers,
+/// or `None` if none of them specifies it.
+fn detect_mode(shares: &[Share]) -> Result<Option<Mode>> {
+    let mut raw = Vec::new();
+    let mut wrapped = Vec::new();
+
+    for share in shares {
+        match share.mode()? {
+            Some(Mode::Raw) => raw.push(share.source().to_string()),
+            Some(Mode::Wrapped) => wrapped.push(share.source().to_string()),
+            None => {}
+        }
+    }
+
+    match (raw.is_empty(), wrapped.is_empty()) {
+        (true, true) => Ok(None),
+        (false, true) => Ok(Some(Mode::Raw)),
+        (true, false) => Ok(Some(Mode::Wrapped)),
+        (false, false) => bail!(ErrorKind::MixedShareModes(raw, wrapped)),
+    }
+}
+
+/// Checks whether a recovered secret is the exact encoding of a `SecretProto`,
+/// which is what shares without a `Mode` header must have been generated from
+/// to be wrapped.
+fn is_wrapped(secret: &[u8]) -> bool {
+    let proto = match protobuf::parse_from_bytes::<SecretProto>(secret) {
+        Ok(proto) => proto,
+        Err(_) => return false,
     };
 
-    match output_path {
-        Some(output_path) => {
-            let mut output_file = File::create(output_path)
-                .chain_err(|| ErrorKind::CannotCreateSecretFile(format!("{}", output_path.display())))?;
-            output_file
-                .write_all(&secret)
-                .chain_err(|| ErrorKind::CannotWriteSecretToFile(format!("{}", output_path.display())))?;
+    if proto.get_unknown_fields().iter().next().is_some() {
+        return false;
+    }
+
+    match proto.write_to_bytes() {
+        Ok(bytes) => bytes == secret,
+        Err(_) => false,
+    }
+}
+
+pub(crate) fn parent_dirs(shares_paths: &[&Path]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+
+    // The shares of a directory are next to the ciphertext, whereas a pattern is relative to its parent
+    let dirs_iter = shares_paths
+        .iter()
+        .filter_map(|path| if path.is_dir() { Some(*path) } else { path.parent() });
+
+    for dir in dirs_iter {
+        if !dirs.iter().any(|d| d == dir) {
+            dirs.push(dir.to_path_buf());
         }
-        None => {
-            // See https://github.com/romac/rustysecrets-cli/issues/9
-            // let secret_str = String::from_utf8(secret)
-            //     .chain_err(|| "Could not parse secret as UTF-8, consider outputting it to a file instead")?;
+    }
+
+    dirs
+}
+
+/// What to decrypt encrypted and protected shares with.
+#[derive(Default)]
+pub struct Keys {
+    /// The age identities of the recipients of encrypted shares
+    pub identities: Vec<Identity>,
+    /// Where to get the passphrases of protected shares from
+    pub passphrases: Passphrases,
+}
+
+pub(crate) fn read_shares(shares_paths: Vec<&Path>, keys: &Keys) -> Result<Vec<Share>> {
+    let mut shares = Vec::with_capacity(shares_paths.len());
+    let mut corrupted = Vec::new();
+    let mut wrong_passphrases = Vec::new();
+
+    for share_path in shares_paths {
+        if is_stdin(share_path) {
+            shares.extend(read_stdin_shares()?);
+            continue;
+        }
+
+        // The files found in a directory or by a pattern may not all be shares
+        let (paths, found) = if is_dir_or_pattern(share_path) {
+            (find_share_files(share_path)?, true)
+        } else {
+            (vec![share_path.to_path_buf()], false)
+        };
+
+        for path in paths {
+            // Keep reading, so that every corrupted share is reported at once
+            match read_share_file(&path, keys) {
+                Ok(read) => shares.extend(read),
+                Err(Error(ErrorKind::CorruptedShares(paths), _)) => corrupted.extend(paths),
+                Err(Error(ErrorKind::WrongPassphrases(paths), _)) => wrong_passphrases.extend(paths),
+                Err(e) if found => warn!("{}, skipping it", e),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    if !corrupted.is_empty() {
+        bail!(ErrorKind::CorruptedShares(corrupted));
+    }
+
+    if !wrong_passphrases.is_empty() {
+        bail!(ErrorKind::WrongPassphrases(wrong_passphrases));
+    }
+
+    Ok(shares)
+}
+
+/// Makes sure that there are enough distinct shares to recover the secret, counting the votes of each holder.
+fn check_votes(shares: &[Share]) -> Result<()> {
+    let threshold = shares[0].threshold;
+
+    let mut ids = shares.iter().map(|share| share.id).collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+
+    if ids.len() >= usize::from(threshold) {
+        return Ok(());
+    }
 
-            io::stdout()
-                .write_all(&secret)
-                .chain_err(|| ErrorKind::CannotWriteSecretToStdout)?;
+    let mut holders: Vec<(String, u8)> = Vec::new();
+    for (i, share) in shares.iter().enumerate() {
+        // The same share may have been given twice
+        if shares[..i].iter().any(|other| other.id == share.id) {
+            continue;
         }
+
+        let holder = share.header("Holder").unwrap_or_else(|| share.source()).to_string();
+        match holders.iter_mut().find(|(name, _)| *name == holder) {
+            Some((_, votes)) => *votes += 1,
+            None => holders.push((holder, 1)),
+        }
+    }
+
+    bail!(ErrorKind::NotEnoughVotes(
+        ids.len() as u8,
+        threshold,
+        holders
+            .iter()
+            .map(|(holder, votes)| format!("{} ({})", holder, votes))
+            .collect()
+    ))
+}
+
+/// Tells which custodians of the set the shares come from, and which ones are absent.
+pub(crate) fn report_custodians(shares: &[Share]) {
+    let mut present: Vec<&str> = Vec::new();
+    for custodian in shares.iter().filter_map(|share| share.header("Custodian")) {
+        if !present.contains(&custodian) {
+            present.push(custodian);
+        }
+    }
+
+    if present.is_empty() {
+        return;
+    }
+
+    info!("Custodians who took part: {}", present.join(", "));
+
+    let absent = shares
+        .iter()
+        .filter_map(|share| share.header("Custodians"))
+        .next()
+        .map_or_else(Vec::new, |all| {
+            all.split(',')
+                .map(str::trim)
+                .filter(|custodian| !present.contains(custodian))
+                .collect()
+        });
+
+    if !absent.is_empty() {
+        warn!("Custodians who were absent: {}", absent.join(", "));
+    }
+}
+
+/// Makes sure that every share is listed in the manifest of the set.
+fn check_manifest(shares: &[Share], manifest: &Manifest) -> Result<()> {
+    let unlisted = shares
+        .iter()
+        .filter(|share| manifest.entry(share).is_none())
+        .map(|share| share.source().to_string())
+        .collect::<Vec<_>>();
+
+    if !unlisted.is_empty() {
+        bail!(ErrorKind::SharesNotInManifest(unlisted));
+    }
+
+    debug!("All the shares are listed in the manifest");
+
+    Ok(())
+}
+
+/// Makes sure that the shares all come from the same split, when they tell which one.
+fn check_share_set(shares: &[Share]) -> Result<()> {
+    let mut sets: Vec<(String, Vec<String>)> = Vec::new();
+
+    for share in shares {
+        if let Some(set_id) = share.set_id() {
+            match sets.iter_mut().find(|(id, _)| *id == set_id) {
+                Some((_, sources)) => sources.push(share.source().to_string()),
+                None => sets.push((set_id, vec![share.source().to_string()])),
+            }
+        }
+    }
+
+    if sets.len() > 1 {
+        bail!(ErrorKind::MixedShareSets(sets));
     }
 
     Ok(())
 }
+
+fn is_stdin(share_path: &Path) -> bool {
+    share_path == Path::new("-")
+}
+
+/// Whether the path is a directory of shares, or a pattern such as `shares/share_*`,
+/// for the shells which do not expand patterns themselves.
+fn is_dir_or_pattern(share_path: &Path) -> bool {
+    if share_path.is_dir() {
+        return true;
+    }
+
+    !share_path.exists() && share_path.to_string_lossy().contains(&['*', '?', '['][..])
+}
+
+/// Lists the files in a directory of shares, or matching a pattern, in order.
+/// Hidden files and the manifest of the set are left out.
+fn find_share_files(share_path: &Path) -> Result<Vec<PathBuf>> {
+    let name = format!("{}", share_path.display());
+
+    let mut paths = if share_path.is_dir() {
+        fs::read_dir(share_path)
+            .chain_err(|| ErrorKind::CannotListShares(name.clone()))?
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry.path()),
+                Err(e) => {
+                    warn!("Cannot list a share in '{}', skipping it: {}", name, e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    } else {
+        glob::glob(&name)
+            .chain_err(|| ErrorKind::InvalidSharesPattern(name.clone()))?
+            .filter_map(|path| match path {
+                Ok(path) => Some(path),
+                Err(e) => {
+                    warn!("{}, skipping it", e);
+                    None
+                }
+            })
+            .collect()
+    };
+
+    paths.retain(|path| {
+        let file_name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        path.is_file() && !file_name.starts_with('.') && file_name != manifest::FILE_NAME
+    });
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!(ErrorKind::NoShareFound(name));
+    }
+
+    debug!("Found {} files in '{}'", paths.len(), name);
+
+    Ok(paths)
+}
+
+/// Reads the shares piped or typed on stdin, separated by blank lines, headers or armor.
+fn read_stdin_shares() -> Result<Vec<Share>> {
+    debug!("Reading shares from stdin... ");
+
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .chain_err(|| ErrorKind::CannotReadShare("<stdin>".to_string()))?;
+
+    let mut shares = Share::parse_all(&content).chain_err(|| ErrorKind::CannotParseShare("<stdin>".to_string()))?;
+
+    for (i, share) in shares.iter_mut().enumerate() {
+        share.source = Some(format!("<stdin>#{}", i + 1));
+    }
+
+    debug!("Read {} shares.", shares.len());
+
+    Ok(shares)
+}
+
+/// Asks for the shares one at a time, until there are enough of them to recover the secret.
+///
+/// Shares are typed on the terminal without echo, or piped on stdin, and each one ends with
+/// a blank line. Each share is checked as soon as it is entered, and rejected if it is invalid
+/// or does not belong with the shares entered before it.
+fn read_interactive_shares() -> Result<Vec<Share>> {
+    let mut shares: Vec<Share> = Vec::new();
+    let mut lines = InteractiveLines::new();
+
+    loop {
+        let prompt = match shares.first() {
+            Some(first) => format!("Share {} of {}, ending with a blank line: ", shares.len() + 1, first.threshold),
+            None => "Share 1, ending with a blank line: ".to_string(),
+        };
+
+        let mut content = String::new();
+        let mut line = lines.next(&prompt)?;
+
+        // Headers and armor are followed by blank lines too, which do not end the share
+        while let Some(text) = line.as_ref() {
+            if text.trim().is_empty() && is_complete(&content) {
+                break;
+            }
+
+            content.push_str(text);
+            content.push('\n');
+            line = lines.next("")?;
+        }
+
+        // The input ended before enough shares were entered
+        if line.is_none() && !is_complete(&content) {
+            match shares.first() {
+                Some(first) => bail!(ErrorKind::NotEnoughShares(shares.len() as u8, first.threshold)),
+                None => bail!(ErrorKind::NoShareEntered),
+            }
+        }
+
+        let mut share = match Share::parse(&content) {
+            Ok(share) => share,
+            Err(e) => {
+                warn!("Invalid share, try again: {}", e);
+                continue;
+            }
+        };
+
+        share.source = Some(format!("<terminal>#{}", shares.len() + 1));
+
+        if share.header("Group").is_some() {
+            bail!(ErrorKind::PolicyUnsupported("Interactive recovery".to_string()));
+        }
+
+        if let Some(first) = shares.first() {
+            if share.threshold != first.threshold || share.set_id() != first.set_id() {
+                warn!("Share #{} does not belong to the same set as the first share, try again", share.id);
+                continue;
+            }
+        }
+
+        if shares.iter().any(|other| other.id == share.id) {
+            warn!("Share #{} was already entered, try again", share.id);
+            continue;
+        }
+
+        let needed = share.threshold.saturating_sub(shares.len() as u8 + 1);
+        shares.push(share);
+
+        if needed == 0 {
+            info!("Got {} shares, recovering the secret", shares.len());
+            return Ok(shares);
+        }
+
+        info!("Share #{} is valid, {} more needed", shares[shares.len() - 1].id, needed);
+    }
+}
+
+/// Whether the lines entered so far hold a whole share, after its headers or the whole armored block.
+fn is_complete(content: &str) -> bool {
+    if armor::is_armored(content) {
+        let count = |marker: &str| content.lines().filter(|line| line.trim().starts_with(marker)).count();
+        return count("-----END") >= count("-----BEGIN");
+    }
+
+    content
+        .lines()
+        .any(|line| !line.trim().is_empty() && !line.contains(':'))
+}
+
+/// The lines of the shares entered by `recover --interactive`.
+enum InteractiveLines {
+    /// Typed on the terminal, without echo
+    Terminal,
+    /// Piped on stdin
+    Stdin(io::StdinLock<'static>),
+}
+
+impl InteractiveLines {
+    fn new() -> InteractiveLines {
+        if io::stdin().is_terminal() {
+            InteractiveLines::Terminal
+        } else {
+            InteractiveLines::Stdin(io::stdin().lock())
+        }
+    }
+
+    /// Reads the next line, or `None` at the end of the input.
+    fn next(&mut self, prompt: &str) -> Result<Option<String>> {
+        match *self {
+            InteractiveLines::Terminal => match rpassword::prompt_password(prompt) {
+                Ok(line) => Ok(Some(line)),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+                Err(e) => Err(e).chain_err(|| ErrorKind::CannotReadShare("<terminal>".to_string())),
+            },
+            InteractiveLines::Stdin(ref mut stdin) => {
+                let mut line = String::new();
+                let len = stdin
+                    .read_line(&mut line)
+                    .chain_err(|| ErrorKind::CannotReadShare("<stdin>".to_string()))?;
+
+                Ok(if len == 0 {
+                    None
+                } else {
+                    Some(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+                })
+            }
+        }
+    }
+}
+
+/// Reads the shares of a file, which holds several shares when its holder has a weight,
+/// decrypting it with the keys if it is encrypted or protected.
+pub(crate) fn read_share_file(share_path: &Path, keys: &Keys) -> Result<Vec<Share>> {
+    if !share_path.exists() {
+        bail!(ErrorKind::ShareDoesNotExists(format!(
+            "{}",
+            share_path.display()
+        ),))
+    }
+    if !share_path.is_file() {
+        bail!(ErrorKind::ShareIsNotAFile(format!(
+            "{}",
+            share_path.display()
+        ),))
+    }
+
+    debug!("Reading share {:?}... ", share_path);
+
+    let mut share_file =
+        File::open(share_path).chain_err(|| ErrorKind::CannotOpenShare(format!("{}", share_path.display())))?;
+
+    let mut content = Vec::new();
+    let size = share_file
+        .read_to_end(&mut content)
+        .chain_err(|| ErrorKind::CannotReadShare(format!("{}", share_path.display())))?;
+
+    debug!("Read {} bytes.", size);
+
+    if age::is_encrypted(&content) {
+        if keys.identities.is_empty() {
+            bail!(ErrorKind::ShareIsEncrypted(format!("{}", share_path.display())));
+        }
+
+        content = age::decrypt(&content, &keys.identities)
+            .chain_err(|| ErrorKind::CannotDecryptShare(format!("{}", share_path.display())))?;
+    }
+
+    if protect::is_protected(&content) {
+        let passphrase = keys.passphrases.next(&format!("'{}'", share_path.display()), false)?;
+
+        content = match protect::unprotect(&content, &passphrase) {
+            Ok(content) => content,
+            Err(Error(ErrorKind::WrongPassphrase, _)) => {
+                bail!(ErrorKind::WrongPassphrases(vec![format!("{}", share_path.display())]))
+            }
+            Err(e) => return Err(e).chain_err(|| ErrorKind::CannotParseShare(format!("{}", share_path.display()))),
+        };
+    }
+
+    // Shares printed as QR codes are read back from their image
+    let share = if qr::is_image(&content) {
+        qr::decode(&content).chain_err(|| ErrorKind::CannotParseShare(format!("{}", share_path.display())))?
+    } else {
+        String::from_utf8(content).chain_err(|| ErrorKind::CannotReadShare(format!("{}", share_path.display())))?
+    };
+
+    let parsed = Share::parse_all(&share).and_then(|shares| {
+        if shares.is_empty() {
+            bail!(ErrorKind::InvalidShare("no share found".to_string()));
+        }
+        Ok(shares)
+    });
+
+    let mut shares = match parsed {
+        Ok(shares) => shares,
+        Err(Error(ErrorKind::ArmorChecksumMismatch, _)) => {
+            bail!(ErrorKind::CorruptedShares(vec![format!("{}", share_path.display())]))
+        }
+        Err(e) => return Err(e).chain_err(|| ErrorKind::CannotParseShare(format!("{}", share_path.display()))),
+    };
+
+    for share in &mut shares {
+        share.source = Some(format!("{}", share_path.display()));
+    }
+
+    Ok(shares)
+}
@@ -0,0 +1,75 @@
+use errors::*;
+use manifest::{self, Manifest};
+use share::Format;
+
+use super::recover::{is_grouped, is_slip39, read_shares, recover_policy_secret, recover_secret, Keys};
+use super::split::{generate_set_id, generate_shares, share_path, today, write_shares};
+use template::Template;
+
+use std::path::Path;
+
+pub fn reshare(
+    shares_paths: Vec<&Path>,
+    output_path: &Path,
+    k: u8,
+    n: u8,
+    sign_shares: bool,
+    share_tmpl: &Template,
+    keys: &Keys,
+) -> Result<()> {
+    if k > n {
+        bail!(ErrorKind::KMustBeSmallerThanN(k, n))
+    }
+
+    // The new set gets its own manifest, which must not replace the manifest of the old one
+    let manifest_path = output_path.join(manifest::FILE_NAME);
+    manifest::check_absent(&manifest_path)?;
+
+    let shares = read_shares(shares_paths, keys)?;
+
+    if is_slip39(&shares) {
+        bail!(ErrorKind::Slip39Unsupported("Resharing".to_string()));
+    }
+    let verify_signatures = shares.iter().any(|share| share.signed);
+    let secret = if is_grouped(&shares) {
+        recover_policy_secret(&shares, verify_signatures, None)?
+    } else {
+        recover_secret(&shares, verify_signatures, None)?
+    };
+
+    // The new shares are written like the old ones
+    let format = shares[0].format;
+
+    if sign_shares && format == Format::Mnemonic {
+        bail!(ErrorKind::SignedMnemonicShares);
+    }
+
+    // The shares of an encrypted secret hold its key, which is all we split again
+    let mut shares = generate_shares(k, n, &secret.data, secret.mode, secret.mime_type.clone(), sign_shares)?;
+
+    // The new shares cannot be mixed with the old ones, so they make a new set
+    let set_id = generate_set_id()?;
+
+    for share in &mut shares {
+        share.format = format;
+        share.set_header("Set-Id", &set_id);
+
+        if let Some(ref ciphertext) = secret.ciphertext {
+            share.set_header("Ciphertext", ciphertext);
+        }
+    }
+
+    let date = today();
+    write_shares(&shares, n, output_path, share_tmpl, &date)?;
+
+    let files = shares
+        .iter()
+        .map(|share| share_path(Path::new(""), share_tmpl, &date, share, n))
+        .collect();
+
+    Manifest::new(&shares, k, n, Vec::new(), secret.mime_type, files).create(&manifest_path)?;
+
+    debug!("Wrote manifest to '{}'", manifest_path.display());
+
+    Ok(())
+}
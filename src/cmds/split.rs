@@ -1,24 +1,97 @@
+use chrono::Local;
 use mime::Mime;
+use ring::rand::{SecureRandom, SystemRandom};
 use rusty_secrets::{sss, wrapped_secrets};
 
+use age::{self, Recipient};
+use cards::{self, Card};
+use custodians::Custodian;
+use envelope;
 use errors::*;
 use input::Input;
+use manifest::{self, Manifest};
+use policy::Policy;
+use protect::{self, Passphrases};
+use progress::Progress;
+use qr;
+use recipients;
+use share::{Format, Mode, Share};
+use slip39;
+use template::{Template, Vars};
+use weights::Holder;
 
-use std::path::Path;
-use std::fs::File;
-use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+
+/// Size above which secrets should rather be encrypted, and only their key split.
+const LARGE_SECRET_SIZE: usize = 16 * 1024 * 1024;
+
+/// Length of the random set identifiers, in bytes.
+const SET_ID_LEN: usize = 4;
+
+/// How to print the shares, besides writing them to files.
+#[derive(Default)]
+pub struct Printouts<'a> {
+    /// The format of the QR code images to write next to the share files
+    pub qr: Option<qr::ImageFormat>,
+    /// Whether to only write the QR code images, and not the share files
+    pub qr_only: bool,
+    /// The PDF file to write a share card per share to
+    pub pdf: Option<&'a Path>,
+    /// The text printed with each share, defaults to the name of the share file
+    pub label: Option<String>,
+}
+
+/// How to protect the share files, so that only their custodian can read them.
+#[derive(Default)]
+pub struct Protection {
+    /// The recipient each share is encrypted to, by number
+    pub recipients: Vec<(u8, Recipient)>,
+    /// Where to get the passphrase each share file is protected with, if any
+    pub passphrases: Option<Passphrases>,
+}
+
+/// How to split the secret, and where to write the shares.
+pub struct SplitOptions<'a> {
+    /// The number of shares necessary to recover the secret
+    pub k: u8,
+    /// The number of shares
+    pub n: u8,
+    pub mime_type: Option<Mime>,
+    pub sign_shares: bool,
+    /// Whether to split the secret without wrapping it along with its MIME type
+    pub raw: bool,
+    pub share_tmpl: &'a Template,
+    /// The file to encrypt the secret to, in which case only its key is split
+    pub ciphertext_path: Option<&'a Path>,
+    pub format: Format,
+    pub slip39_params: Option<slip39::Params>,
+    pub policy: Option<Policy>,
+    pub holders: Option<Vec<Holder>>,
+    pub custodians: Option<Vec<Custodian>>,
+    pub protection: Protection,
+    pub printouts: Printouts<'a>,
+}
+
+pub fn split(mut secret_input: Input, output_path: &Path, options: SplitOptions) -> Result<()> {
+    let SplitOptions {
+        k,
+        n,
+        mime_type,
+        sign_shares,
+        raw,
+        share_tmpl,
+        ciphertext_path,
+        format,
+        slip39_params,
+        policy,
+        holders,
+        custodians,
+        protection,
+        printouts,
+    } = options;
 
-#[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
-pub fn split(
-    mut secret_input: Input,
-    output_path: &Path,
-    k: u8,
-    n: u8,
-    mime_type: Option<Mime>,
-    sign_shares: bool,
-    raw: bool,
-    share_tmpl: &str,
-) -> Result<()> {
     if k > n {
         bail!(ErrorKind::KMustBeSmallerThanN(k, n))
     }
@@ -27,6 +100,196 @@ pub fn split(
         bail!(ErrorKind::RawMimeConflict);
     }
 
+    if sign_shares && format == Format::Mnemonic {
+        bail!(ErrorKind::SignedMnemonicShares);
+    }
+
+    if sign_shares && (printouts.qr.is_some() || printouts.pdf.is_some()) {
+        bail!(ErrorKind::SignedSharesPrintout);
+    }
+
+    if slip39_params.is_some() {
+        if sign_shares {
+            bail!(ErrorKind::Slip39Unsupported("Signing".to_string()));
+        }
+        if mime_type.is_some() {
+            bail!(ErrorKind::Slip39Unsupported("A MIME type".to_string()));
+        }
+        if policy.is_some() {
+            bail!(ErrorKind::Slip39Unsupported("A policy".to_string()));
+        }
+        if holders.is_some() {
+            bail!(ErrorKind::Slip39Unsupported("Weights".to_string()));
+        }
+    }
+
+    if !protection.recipients.is_empty() {
+        recipients::check(&protection.recipients, n)?;
+    }
+
+    // The shares of another set may be in the output directory already
+    let manifest_path = output_path.join(manifest::FILE_NAME);
+    manifest::check_absent(&manifest_path)?;
+
+    let date = today();
+
+    let mode = if raw { Mode::Raw } else { Mode::Wrapped };
+    let mime_type = mime_type.map(|m| m.as_ref().to_string());
+
+    let (secret, ciphertext_name) = match ciphertext_path {
+        Some(ciphertext_path) => {
+            let key = envelope::generate_key()?;
+
+            encrypt_secret(&key, &mut secret_input, ciphertext_path)?;
+
+            let ciphertext_name = ciphertext_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            (key, Some(ciphertext_name))
+        }
+        None => (read_secret(secret_input)?, None),
+    };
+
+    // Groups are recorded as the number of groups needed out of the groups
+    let (manifest_k, manifest_n, groups) = match (&slip39_params, &policy) {
+        (Some(params), _) if params.groups.len() > 1 => {
+            (params.group_threshold, params.groups.len() as u8, params.groups.clone())
+        }
+        (_, Some(policy)) => (
+            policy.threshold,
+            policy.groups.len() as u8,
+            policy.groups.iter().map(|group| (group.k, group.n)).collect(),
+        ),
+        _ => (k, n, Vec::new()),
+    };
+
+    // The number of shares in the group of each share, for the captions of the QR codes
+    let (mut shares, counts) = match (slip39_params, policy) {
+        (Some(params), _) => (
+            generate_slip39_shares(&secret, &params)?,
+            params
+                .groups
+                .iter()
+                .flat_map(|&(_, count)| vec![count; count as usize])
+                .collect(),
+        ),
+        (None, Some(policy)) => (
+            generate_policy_shares(&secret, &policy, mode, mime_type.clone(), sign_shares)?,
+            policy
+                .groups
+                .iter()
+                .flat_map(|group| vec![group.n; group.n as usize])
+                .collect(),
+        ),
+        (None, None) => (
+            generate_shares(k, n, &secret, mode, mime_type.clone(), sign_shares)?,
+            vec![n; n as usize],
+        ),
+    };
+
+    // Holders get as many consecutive shares as their weight
+    if let Some(ref holders) = holders {
+        let names = holders
+            .iter()
+            .flat_map(|holder| vec![holder.name.as_str(); holder.weight as usize]);
+
+        for (share, name) in shares.iter_mut().zip(names) {
+            share.set_header("Holder", name);
+        }
+    }
+
+    if let Some(ref custodians) = custodians {
+        let names = custodians
+            .iter()
+            .map(|custodian| custodian.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        for (share, custodian) in shares.iter_mut().zip(custodians) {
+            share.set_header("Custodian", &custodian.name);
+            if let Some(ref contact) = custodian.contact {
+                share.set_header("Contact", contact);
+            }
+            share.set_header("Custodians", &names);
+        }
+    }
+
+    // Shares go to the directory of their custodian, if any
+    let dirs = match custodians {
+        Some(ref custodians) => custodians
+            .iter()
+            .map(|custodian| custodian.path.as_ref().map_or(output_path, PathBuf::as_path))
+            .collect(),
+        None => vec![output_path; shares.len()],
+    };
+
+    // SLIP-39 shares carry their own identifier
+    let set_id = if format == Format::Slip39 {
+        None
+    } else {
+        Some(generate_set_id()?)
+    };
+
+    for share in &mut shares {
+        share.format = format;
+
+        if let Some(ref set_id) = set_id {
+            share.set_header("Set-Id", set_id);
+        }
+
+        if let Some(ref ciphertext_name) = ciphertext_name {
+            share.set_header("Ciphertext", ciphertext_name);
+        }
+
+        // Armored shares describe the secret they hold
+        if let (Format::Armor, Some(mime_type)) = (format, &mime_type) {
+            share.set_header("Mime-Type", mime_type);
+        }
+    }
+
+    if !printouts.qr_only {
+        write_shares_to(&shares, &counts, &dirs, &protection, share_tmpl, &date)?;
+    }
+
+    if let Some(format) = printouts.qr {
+        write_qr_codes(&shares, &counts, &dirs, share_tmpl, &date, format, &printouts.label)?;
+    }
+
+    if let Some(pdf_path) = printouts.pdf {
+        write_cards(&shares, &counts, share_tmpl, &date, pdf_path, &printouts.label)?;
+    }
+
+    let files = shares
+        .iter()
+        .zip(&counts)
+        .zip(&dirs)
+        .map(|((share, &count), &dir)| {
+            // Files outside of the output directory are recorded with their full path
+            let dir = if dir == output_path { Path::new("") } else { dir };
+
+            // Only the QR codes are left to be checked against the manifest
+            let mut file = share_path(dir, share_tmpl, &date, share, count);
+            if let (true, Some(format)) = (printouts.qr_only, printouts.qr) {
+                file.set_extension(format.extension());
+            }
+            if recipient(&protection.recipients, share).is_some() {
+                file = encrypted_path(file);
+            }
+            file
+        })
+        .collect();
+
+    let manifest = Manifest::new(&shares, manifest_k, manifest_n, groups, mime_type, files);
+    manifest.create(&manifest_path)?;
+
+    debug!("Wrote manifest to '{}'", manifest_path.display());
+
+    Ok(())
+}
+
+fn read_secret(mut secret_input: Input) -> Result<Vec<u8>> {
     debug!("Reading secret...");
 
     let mut secret = Vec::new();
@@ -36,31 +299,370 @@ pub fn split(
 
     debug!("Read {} bytes.", size);
 
+    // Every share is as large as the secret, which RustySecrets splits in memory
+    if size > LARGE_SECRET_SIZE {
+        warn!("The secret is {} bytes long, consider splitting it with --encrypt", size);
+    }
+
+    Ok(secret)
+}
+
+/// Streams the secret through the encryption, so that it is never held in memory.
+fn encrypt_secret(key: &[u8], secret_input: &mut Input, ciphertext_path: &Path) -> Result<()> {
+    debug!("Encrypting secret to '{}'...", ciphertext_path.display());
+
+    let ciphertext_file = File::create(ciphertext_path)
+        .chain_err(|| ErrorKind::CannotCreateCiphertextFile(format!("{}", ciphertext_path.display())))?;
+
+    let mut progress = Progress::new("Encrypted", secret_input.size());
+
+    envelope::seal(key, secret_input, BufWriter::new(ciphertext_file), &mut progress)
+        .chain_err(|| ErrorKind::CannotWriteCiphertextToFile(format!("{}", ciphertext_path.display())))?;
+
+    info!("Wrote encrypted secret to '{}'", ciphertext_path.display());
+
+    Ok(())
+}
+
+pub(crate) fn generate_shares(
+    k: u8,
+    n: u8,
+    secret: &[u8],
+    mode: Mode,
+    mime_type: Option<String>,
+    sign_shares: bool,
+) -> Result<Vec<Share>> {
     debug!("Generating shares...");
 
-    let shares = if raw {
-        sss::split_secret(k, n, &secret, sign_shares)
-    } else {
-        let mime_type = mime_type.map(|m| m.as_ref().to_string());
-        wrapped_secrets::split_secret(k, n, &secret, mime_type, sign_shares)
+    let shares = match mode {
+        Mode::Raw => sss::split_secret(k, n, secret, sign_shares),
+        Mode::Wrapped => wrapped_secrets::split_secret(k, n, secret, mime_type, sign_shares),
     }.chain_err(|| "Could not generate shares")?;
 
-    for (num, share) in shares.iter().enumerate() {
-        let mut path_buf = output_path.to_path_buf();
-        path_buf.push(share_tmpl.replace("{{num}}", &format!("{}", num)));
+    shares.into_iter().map(|share| Share::new(share, mode)).collect()
+}
+
+/// Generates the identifier telling the shares of a split apart from the shares of other splits.
+pub(crate) fn generate_set_id() -> Result<String> {
+    let mut set_id = [0; SET_ID_LEN];
+
+    SystemRandom::new()
+        .fill(&mut set_id)
+        .map_err(|_| ErrorKind::CannotGenerateRandomBytes)?;
+
+    Ok(set_id.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Splits the secret into a share per group of the policy, then splits the share of each group between its members.
+fn generate_policy_shares(
+    secret: &[u8],
+    policy: &Policy,
+    mode: Mode,
+    mime_type: Option<String>,
+    sign_shares: bool,
+) -> Result<Vec<Share>> {
+    let group_shares = generate_shares(
+        policy.threshold,
+        policy.groups.len() as u8,
+        secret,
+        mode,
+        mime_type,
+        sign_shares,
+    )?;
+
+    let mut shares = Vec::new();
+
+    for (group, group_share) in policy.groups.iter().zip(&group_shares) {
+        debug!("Splitting the share of group '{}'...", group.name);
+
+        let members = generate_shares(group.k, group.n, group_share.as_str().as_bytes(), Mode::Raw, None, sign_shares)?;
+
+        for mut share in members {
+            // The shares of the group are raw, but the secret they eventually recover may not be
+            share.set_header("Mode", &mode.to_string());
+            share.set_header("Group", &group.name);
+            share.set_header("Policy", &policy.to_string());
+            shares.push(share);
+        }
+    }
+
+    Ok(shares)
+}
+
+/// Splits the secret into SLIP-39 shares, numbered across all groups.
+fn generate_slip39_shares(secret: &[u8], params: &slip39::Params) -> Result<Vec<Share>> {
+    debug!("Generating SLIP-39 shares...");
+
+    let groups = slip39::split(secret, params)?;
+
+    Ok(groups
+        .iter()
+        .flat_map(|group| group.iter())
+        .enumerate()
+        .map(|(i, share)| {
+            let mut share = Share::from_slip39(share);
+            // Files are named after the share identifiers, which must be unique
+            share.id = i as u8 + 1;
+            share
+        })
+        .collect())
+}
+
+/// Writes the shares of a set of `n` shares to the output directory.
+pub(crate) fn write_shares(
+    shares: &[Share],
+    n: u8,
+    output_path: &Path,
+    share_tmpl: &Template,
+    date: &str,
+) -> Result<()> {
+    let (counts, dirs) = (vec![n; shares.len()], vec![output_path; shares.len()]);
+
+    write_shares_to(shares, &counts, &dirs, &Protection::default(), share_tmpl, date)
+}
+
+/// Writes each share to the given directory, given the number of shares in its group,
+/// protected by a passphrase and encrypted to its recipient if need be.
+fn write_shares_to(
+    shares: &[Share],
+    counts: &[u8],
+    dirs: &[&Path],
+    protection: &Protection,
+    share_tmpl: &Template,
+    date: &str,
+) -> Result<()> {
+    let recipients = &protection.recipients;
+
+    let paths = shares
+        .iter()
+        .zip(counts)
+        .zip(dirs)
+        .map(|((share, &count), dir)| {
+            let path_buf = share_path(dir, share_tmpl, date, share, count);
+            match recipient(recipients, share) {
+                Some(_) => encrypted_path(path_buf),
+                None => path_buf,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    check_collisions(shares, &paths)?;
+
+    // The shares of a holder all go to the same file, separated by blank lines
+    let mut files: Vec<(PathBuf, String, &Share)> = Vec::new();
+
+    for (share, path_buf) in shares.iter().zip(paths) {
+        debug!("Writing share #{} to '{}'...", share.id, path_buf.display());
+
+        match files.iter_mut().find(|(path, _, _)| *path == path_buf) {
+            Some((_, content, _)) => {
+                content.push('\n');
+                content.push_str(&share.to_string());
+            }
+            None => files.push((path_buf, share.to_string(), share)),
+        }
+    }
+
+    // Every passphrase is asked for before any file is written
+    for (path_buf, content, share) in &mut files {
+        if let Some(ref passphrases) = protection.passphrases {
+            let passphrase = passphrases.next(&format!("'{}'", path_buf.display()), true)?;
+            *content = protect::protect(content.as_bytes(), &passphrase)?;
+        }
+
+        if let Some(recipient) = recipient(recipients, share) {
+            debug!("Encrypting share #{} to '{}'...", share.id, recipient);
+            *content = age::encrypt(content.as_bytes(), recipient)?;
+        }
+    }
+
+    for (path_buf, content, _) in files {
         let share_path = path_buf.as_path();
 
-        debug!("Writing share #{} to '{}'...", num, share_path.display());
+        create_parent_dir(share_path)?;
 
         let mut share_file = File::create(share_path)
             .chain_err(|| ErrorKind::CannotCreateShareFile(format!("{}", share_path.display())))?;
 
         share_file
-            .write_all(share.as_bytes())
+            .write_all(content.as_bytes())
             .chain_err(|| ErrorKind::CannotWriteShareDataToFile(format!("{}", share_path.display())))?;
     }
 
-    info!("Wrote {} shares to '{}'", n, output_path.display());
+    info!("Wrote {} shares to {}", shares.len(), destination(dirs));
 
     Ok(())
 }
+
+/// Writes a QR code of each share next to its file, captioned with its index, threshold and label.
+fn write_qr_codes(
+    shares: &[Share],
+    counts: &[u8],
+    dirs: &[&Path],
+    share_tmpl: &Template,
+    date: &str,
+    format: qr::ImageFormat,
+    label: &Option<String>,
+) -> Result<()> {
+    let paths = shares
+        .iter()
+        .zip(counts)
+        .zip(dirs)
+        .map(|((share, &count), dir)| {
+            let mut path_buf = share_path(dir, share_tmpl, date, share, count);
+            path_buf.set_extension(format.extension());
+            path_buf
+        })
+        .collect::<Vec<_>>();
+
+    check_collisions(shares, &paths)?;
+
+    for ((share, count), path_buf) in shares.iter().zip(counts).zip(&paths) {
+        let qr_path = path_buf.as_path();
+
+        debug!("Writing QR code of share #{} to '{}'...", share.id, qr_path.display());
+
+        let caption = vec![
+            format!("Share #{} - {}/{}", share.id, share.threshold, count),
+            share_label(share, *count, share_tmpl, date, label),
+        ];
+        let image = qr::render(&share.to_string(), &caption, format)
+            .chain_err(|| ErrorKind::CannotWriteShareDataToFile(format!("{}", qr_path.display())))?;
+
+        create_parent_dir(qr_path)?;
+
+        let mut qr_file =
+            File::create(qr_path).chain_err(|| ErrorKind::CannotCreateShareFile(format!("{}", qr_path.display())))?;
+
+        qr_file
+            .write_all(&image)
+            .chain_err(|| ErrorKind::CannotWriteShareDataToFile(format!("{}", qr_path.display())))?;
+    }
+
+    info!("Wrote {} QR codes to {}", shares.len(), destination(dirs));
+
+    Ok(())
+}
+
+/// Writes a PDF document with a printable card for each share.
+fn write_cards(
+    shares: &[Share],
+    counts: &[u8],
+    share_tmpl: &Template,
+    date: &str,
+    pdf_path: &Path,
+    label: &Option<String>,
+) -> Result<()> {
+    debug!("Writing share cards to '{}'...", pdf_path.display());
+    let cards = shares
+        .iter()
+        .zip(counts)
+        .map(|(share, &count)| Card {
+            share,
+            label: share_label(share, count, share_tmpl, date, label),
+            count,
+            date: date.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    let pdf = cards::render(&cards).chain_err(|| ErrorKind::CannotWriteCards(format!("{}", pdf_path.display())))?;
+
+    fs::write(pdf_path, pdf).chain_err(|| ErrorKind::CannotWriteCards(format!("{}", pdf_path.display())))?;
+
+    info!("Wrote {} share cards to '{}'", shares.len(), pdf_path.display());
+
+    Ok(())
+}
+
+/// Where the files were written to, for use in messages.
+fn destination(dirs: &[&Path]) -> String {
+    match dirs.first() {
+        Some(first) if dirs.iter().all(|dir| dir == first) => format!("'{}'", first.display()),
+        _ => "the directories of the custodians".to_string(),
+    }
+}
+
+/// The label printed with a share, which defaults to the name of its custodian, or of its file.
+fn share_label(share: &Share, count: u8, share_tmpl: &Template, date: &str, label: &Option<String>) -> String {
+    label
+        .clone()
+        .or_else(|| share.header("Custodian").map(String::from))
+        .unwrap_or_else(|| {
+            share_path(Path::new(""), share_tmpl, date, share, count)
+                .to_string_lossy()
+                .into_owned()
+        })
+}
+
+/// The recipient a share is encrypted to, if any.
+fn recipient<'a>(recipients: &'a [(u8, Recipient)], share: &Share) -> Option<&'a Recipient> {
+    recipients
+        .iter()
+        .find(|&&(num, _)| num == share.id)
+        .map(|(_, recipient)| recipient)
+}
+
+/// The path of an encrypted share, which gets the extension of age files after its own.
+fn encrypted_path(path_buf: PathBuf) -> PathBuf {
+    let mut path = path_buf.into_os_string();
+    path.push(".age");
+
+    PathBuf::from(path)
+}
+
+/// Makes sure that no two shares are written to the same file, unless they belong to the same holder.
+fn check_collisions(shares: &[Share], paths: &[PathBuf]) -> Result<()> {
+    for (i, path) in paths.iter().enumerate() {
+        if let Some(j) = paths[..i].iter().position(|other| other == path) {
+            let (first, share) = (&shares[j], &shares[i]);
+            let holder = share.header("Holder");
+
+            if holder.is_none() || holder != first.header("Holder") {
+                bail!(ErrorKind::ShareFileCollision(
+                    format!("{}", path.display()),
+                    first.id,
+                    share.id
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the directory of a share, such as the directory of its group, if it does not exist yet.
+fn create_parent_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => {
+            fs::create_dir_all(dir).chain_err(|| ErrorKind::CannotCreateShareFile(format!("{}", path.display())))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The date of the shares written by a command, taken once so that all their file names and cards agree.
+pub(crate) fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Returns the path of the file a share of a group of `n` shares is written to, in the directory of its group if any.
+pub(crate) fn share_path(output_path: &Path, share_tmpl: &Template, date: &str, share: &Share, n: u8) -> PathBuf {
+    let mut path_buf = output_path.to_path_buf();
+    if let Some(group) = share.header("Group") {
+        path_buf.push(group);
+    }
+
+    let set_id = share.set_id().unwrap_or_default();
+
+    path_buf.push(share_tmpl.render(&Vars {
+        id: share.id,
+        holder: share.header("Holder"),
+        k: share.threshold,
+        n,
+        set_id: &set_id,
+        date,
+        custodian: share.header("Custodian").unwrap_or(""),
+        ext: share.format.extension(),
+    }));
+    path_buf
+}
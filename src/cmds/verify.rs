@@ -0,0 +1,55 @@
+use digest::{sha256_hex, Sha256Writer};
+use errors::*;
+use share::Mode;
+
+use super::recover::{decrypt_ciphertext, find_ciphertext, is_grouped, is_slip39, parent_dirs, read_shares,
+                     recover_policy_secret, recover_secret, recover_slip39_secret, report_custodians, Keys};
+
+use std::path::Path;
+
+pub fn verify(
+    shares_paths: Vec<&Path>,
+    mode: Option<Mode>,
+    ciphertext_path: Option<&Path>,
+    passphrase: &str,
+    keys: &Keys,
+) -> Result<()> {
+    let shares_dirs = parent_dirs(&shares_paths);
+    let shares = read_shares(shares_paths, keys).chain_err(|| ErrorKind::VerificationFailed)?;
+
+    report_custodians(&shares);
+
+    // Signatures are checked whenever the shares carry them,
+    // which makes RustySecrets reject a partially signed set.
+    let verify_signatures = shares.iter().any(|share| share.signed);
+
+    let secret = if is_slip39(&shares) {
+        recover_slip39_secret(&shares, passphrase)
+    } else if is_grouped(&shares) {
+        recover_policy_secret(&shares, verify_signatures, mode)
+    } else {
+        recover_secret(&shares, verify_signatures, mode)
+    };
+
+    let fingerprint = secret
+        .and_then(|secret| match find_ciphertext(&secret, &shares_dirs, ciphertext_path)? {
+            Some(ciphertext_path) => {
+                // Encrypted secrets are hashed as they are decrypted, without holding them in memory
+                let mut digest = Sha256Writer::new();
+                decrypt_ciphertext(&secret.data, &ciphertext_path, &mut digest)?;
+                Ok(digest.finish_hex())
+            }
+            None => Ok(sha256_hex(&secret.data)),
+        })
+        .chain_err(|| ErrorKind::VerificationFailed)?;
+
+    println!("SHA-256: {}", fingerprint);
+
+    if verify_signatures {
+        info!("Recovered the secret from {} signed shares", shares.len());
+    } else {
+        info!("Recovered the secret from {} unsigned shares", shares.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! Custodians files, which name the holder of each share, and where to write it:
+//!
+//! ```text
+//! [
+//!   { "name": "Alice", "contact": "alice@example.com", "path": "/media/alice" },
+//!   { "name": "Bob", "contact": "+41 79 000 00 00" },
+//!   { "name": "Carol" }
+//! ]
+//! ```
+//!
+//! Names may only contain letters, digits, '-' and '_', as they may be part of file names.
+//! Each share is written to the directory of its custodian, or to the output directory,
+//! with their name and contact in its `Custodian` and `Contact` headers. The names of all
+//! the custodians are also listed in the `Custodians` header, so that `recover` can tell
+//! who is missing.
+
+use serde_json;
+
+use errors::*;
+use policy;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Custodian {
+    pub name: String,
+    /// How to reach the custodian
+    pub contact: Option<String>,
+    /// The directory to write the share of the custodian to, instead of the output directory
+    pub path: Option<PathBuf>,
+}
+
+/// Reads the custodians listed in the file, in the order of the shares.
+pub fn load(path: &Path) -> Result<Vec<Custodian>> {
+    let content =
+        fs::read_to_string(path).chain_err(|| ErrorKind::CannotReadCustodians(format!("{}", path.display())))?;
+
+    let mut custodians: Vec<Custodian> = serde_json::from_str(&content)
+        .chain_err(|| ErrorKind::InvalidCustodians(format!("{}", path.display()), "invalid JSON".to_string()))?;
+
+    let invalid = |reason: String| ErrorKind::InvalidCustodians(format!("{}", path.display()), reason);
+
+    if custodians.is_empty() || custodians.len() > usize::from(u8::MAX) {
+        bail!(invalid(format!("expected between 1 and {} custodians", u8::MAX)));
+    }
+
+    // Header values are read back trimmed
+    for custodian in &mut custodians {
+        custodian.name = custodian.name.trim().to_string();
+        custodian.contact = custodian.contact.as_ref().map(|contact| contact.trim().to_string());
+    }
+
+    for (i, custodian) in custodians.iter().enumerate() {
+        // Names end up in share headers, separated by commas, and in file names through `{{custodian}}`
+        if !policy::is_valid_name(&custodian.name) {
+            bail!(invalid(format!(
+                "'{}' may only contain letters, digits, '-' and '_'",
+                custodian.name
+            )));
+        }
+
+        if let Some(ref contact) = custodian.contact {
+            if contact.contains(&['\n', '\r'][..]) {
+                bail!(invalid(format!("invalid contact of '{}'", custodian.name)));
+            }
+        }
+
+        if custodians[..i].iter().any(|other| other.name == custodian.name) {
+            bail!(invalid(format!("'{}' is listed twice", custodian.name)));
+        }
+
+        if let Some(ref dir) = custodian.path {
+            if !dir.is_dir() {
+                bail!(invalid(format!(
+                    "the path of '{}' is not a directory: {}",
+                    custodian.name,
+                    dir.display()
+                )));
+            }
+        }
+    }
+
+    Ok(custodians)
+}
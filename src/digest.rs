@@ -0,0 +1,48 @@
+use ring::digest::{self, Context, SHA256};
+
+use std::io::{self, Write};
+
+/// Returns the hex-encoded SHA-256 digest of the given data.
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(digest::digest(&SHA256, data).as_ref())
+}
+
+/// Computes the SHA-256 digest of everything written to it.
+pub struct Sha256Writer {
+    context: Context,
+}
+
+impl Sha256Writer {
+    pub fn new() -> Self {
+        Sha256Writer {
+            context: Context::new(&SHA256),
+        }
+    }
+
+    /// Returns the hex-encoded digest of the data written so far.
+    pub fn finish_hex(self) -> String {
+        to_hex(self.context.finish().as_ref())
+    }
+}
+
+impl Default for Sha256Writer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for Sha256Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.context.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the lowercase hex encoding of the bytes.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,250 @@
+//! Encryption of secrets with a random data key, so that only the key needs to be split.
+//!
+//! Secrets are encrypted with ChaCha20-Poly1305 in chunks of 64 KiB, so that
+//! they can be streamed without holding them in memory. An envelope is laid out as follows:
+//!
+//! ```text
+//! RSENC\x01 | nonce prefix (7 bytes) | chunk 0 | chunk 1 | ... | last chunk
+//! ```
+//!
+//! Each chunk holds its ciphertext followed by a 16 bytes tag, and is encrypted with
+//! the nonce `prefix | counter (4 bytes, big-endian) | last (1 byte)`, which prevents
+//! chunks from being reordered, and the envelope from being truncated.
+//! The last chunk is always shorter than the others, and may be empty.
+
+use ring::aead::{self, OpeningKey, SealingKey, CHACHA20_POLY1305};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use errors::*;
+use progress::Progress;
+
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8] = b"RSENC\x01";
+const NONCE_PREFIX_LEN: usize = 7;
+const NONCE_LEN: usize = 12;
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Length of the data keys, in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// Generates a random data key.
+pub fn generate_key() -> Result<Vec<u8>> {
+    let mut key = vec![0; KEY_LEN];
+
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| ErrorKind::CannotGenerateRandomBytes)?;
+
+    Ok(key)
+}
+
+/// Encrypts everything read from `plaintext` into an envelope written to `envelope`.
+pub fn seal<R: Read, W: Write>(key: &[u8], mut plaintext: R, mut envelope: W, progress: &mut Progress) -> Result<()> {
+    let key = SealingKey::new(&CHACHA20_POLY1305, key).map_err(|_| ErrorKind::CannotEncryptSecret)?;
+    let tag_len = CHACHA20_POLY1305.tag_len();
+
+    let mut prefix = [0; NONCE_PREFIX_LEN];
+    SystemRandom::new()
+        .fill(&mut prefix)
+        .map_err(|_| ErrorKind::CannotGenerateRandomBytes)?;
+
+    envelope
+        .write_all(MAGIC)
+        .and_then(|_| envelope.write_all(&prefix))
+        .chain_err(|| "Cannot write the envelope")?;
+
+    let mut chunk = vec![0; CHUNK_LEN + tag_len];
+
+    for counter in 0.. {
+        let len = read_full(&mut plaintext, &mut chunk[..CHUNK_LEN]).chain_err(|| "Cannot read the plaintext")?;
+        let last = len < CHUNK_LEN;
+
+        let nonce = chunk_nonce(&prefix, counter, last)?;
+        let sealed_len = aead::seal_in_place(&key, &nonce, &[], &mut chunk[..len + tag_len], tag_len)
+            .map_err(|_| ErrorKind::CannotEncryptSecret)?;
+
+        envelope
+            .write_all(&chunk[..sealed_len])
+            .chain_err(|| "Cannot write the envelope")?;
+
+        progress.advance(len);
+
+        if last {
+            break;
+        }
+    }
+
+    envelope.flush().chain_err(|| "Cannot write the envelope")?;
+    progress.finish();
+
+    Ok(())
+}
+
+/// Decrypts an envelope read from `envelope` into `plaintext`, checking that it has not been tampered with.
+///
+/// Chunks are written out as soon as they are authenticated, so `plaintext` may have
+/// received part of the secret when the envelope turns out to be truncated or corrupted.
+pub fn open<R: Read, W: Write>(key: &[u8], mut envelope: R, mut plaintext: W, progress: &mut Progress) -> Result<()> {
+    let key = OpeningKey::new(&CHACHA20_POLY1305, key).map_err(|_| ErrorKind::InvalidEnvelope)?;
+    let tag_len = CHACHA20_POLY1305.tag_len();
+
+    let mut header = vec![0; MAGIC.len() + NONCE_PREFIX_LEN];
+    let header_len = read_full(&mut envelope, &mut header).chain_err(|| "Cannot read the envelope")?;
+
+    if header_len < header.len() || &header[..MAGIC.len()] != MAGIC {
+        bail!(ErrorKind::InvalidEnvelope);
+    }
+
+    let prefix = &header[MAGIC.len()..];
+    let mut chunk = vec![0; CHUNK_LEN + tag_len];
+
+    for counter in 0.. {
+        let len = read_full(&mut envelope, &mut chunk).chain_err(|| "Cannot read the envelope")?;
+        let last = len < chunk.len();
+
+        if len < tag_len {
+            bail!(ErrorKind::InvalidEnvelope);
+        }
+
+        let nonce = chunk_nonce(prefix, counter, last)?;
+        let opened =
+            aead::open_in_place(&key, &nonce, &[], 0, &mut chunk[..len]).map_err(|_| ErrorKind::InvalidEnvelope)?;
+
+        plaintext
+            .write_all(opened)
+            .chain_err(|| "Cannot write the plaintext")?;
+
+        progress.advance(opened.len());
+
+        if last {
+            break;
+        }
+    }
+
+    plaintext.flush().chain_err(|| "Cannot write the plaintext")?;
+    progress.finish();
+
+    Ok(())
+}
+
+fn chunk_nonce(prefix: &[u8], counter: u64, last: bool) -> Result<[u8; NONCE_LEN]> {
+    if counter >> 32 != 0 {
+        bail!(ErrorKind::InvalidEnvelope);
+    }
+
+    let mut nonce = [0; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+
+    for i in 0..4 {
+        nonce[NONCE_PREFIX_LEN + i] = (counter >> (8 * (3 - i))) as u8;
+    }
+
+    nonce[NONCE_LEN - 1] = u8::from(last);
+
+    Ok(nonce)
+}
+
+/// Reads until `buf` is full or the end of the input is reached, and returns the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG_LEN: usize = 16;
+    const HEADER_LEN: usize = 6 + NONCE_PREFIX_LEN;
+
+    fn seal_bytes(key: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut envelope = Vec::new();
+        seal(key, plaintext, &mut envelope, &mut Progress::new("Encrypted", None)).unwrap();
+        envelope
+    }
+
+    fn open_bytes(key: &[u8], envelope: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        open(key, envelope, &mut plaintext, &mut Progress::new("Decrypted", None))?;
+        Ok(plaintext)
+    }
+
+    fn is_invalid(result: Result<Vec<u8>>) -> bool {
+        matches!(result, Err(Error(ErrorKind::InvalidEnvelope, _)))
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = generate_key().unwrap();
+
+        // Empty, shorter than a chunk, exactly a chunk, and several chunks
+        for &len in &[0, 100, CHUNK_LEN, 2 * CHUNK_LEN + 10] {
+            let plaintext = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+            let envelope = seal_bytes(&key, &plaintext);
+
+            assert_eq!(&envelope[..MAGIC.len()], MAGIC);
+            assert_eq!(open_bytes(&key, &envelope).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn wrong_key() {
+        let envelope = seal_bytes(&generate_key().unwrap(), b"secret");
+
+        assert!(is_invalid(open_bytes(&generate_key().unwrap(), &envelope)));
+    }
+
+    #[test]
+    fn truncated() {
+        let key = generate_key().unwrap();
+        let envelope = seal_bytes(&key, &vec![0; 2 * CHUNK_LEN + 10]);
+
+        // Dropping the last chunk leaves a full chunk at the end, which is not marked as the last one
+        let full_chunks = HEADER_LEN + 2 * (CHUNK_LEN + TAG_LEN);
+        assert!(is_invalid(open_bytes(&key, &envelope[..full_chunks])));
+
+        assert!(is_invalid(open_bytes(&key, &envelope[..envelope.len() - 1])));
+        assert!(is_invalid(open_bytes(&key, &envelope[..HEADER_LEN])));
+        assert!(is_invalid(open_bytes(&key, &envelope[..3])));
+    }
+
+    #[test]
+    fn reordered_chunks() {
+        let key = generate_key().unwrap();
+        let plaintext = (0..2 * CHUNK_LEN + 10).map(|i| (i / CHUNK_LEN) as u8).collect::<Vec<_>>();
+        let envelope = seal_bytes(&key, &plaintext);
+
+        let chunk = CHUNK_LEN + TAG_LEN;
+        let mut reordered = envelope[..HEADER_LEN].to_vec();
+        reordered.extend_from_slice(&envelope[HEADER_LEN + chunk..HEADER_LEN + 2 * chunk]);
+        reordered.extend_from_slice(&envelope[HEADER_LEN..HEADER_LEN + chunk]);
+        reordered.extend_from_slice(&envelope[HEADER_LEN + 2 * chunk..]);
+
+        assert_eq!(reordered.len(), envelope.len());
+        assert!(is_invalid(open_bytes(&key, &reordered)));
+    }
+
+    #[test]
+    fn tampered() {
+        let key = generate_key().unwrap();
+        let mut envelope = seal_bytes(&key, b"secret");
+
+        envelope[HEADER_LEN] ^= 1;
+        assert!(is_invalid(open_bytes(&key, &envelope)));
+
+        let mut envelope = seal_bytes(&key, b"secret");
+        envelope[0] = b'X';
+        assert!(is_invalid(open_bytes(&key, &envelope)));
+    }
+}
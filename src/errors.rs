@@ -1,4 +1,3 @@
-#![cfg_attr(feature = "cargo-clippy", allow(unused_doc_comment))]
 #![allow(unknown_lints)]
 
 use input::Input;
@@ -19,6 +18,14 @@ error_chain! {
             description("cannot open secret file")
             display("cannot open secret file '{}'", path)
         }
+        CannotOpenSecret(input: String) {
+            description("Cannot open secret")
+            display("Cannot open secret from '{}'", input)
+        }
+        InvalidSeed(len: usize) {
+            description("Cannot derive a public key from the secret")
+            display("Cannot derive a public key from a {} byte secret, expected 32 bytes or 64 hexadecimal digits", len)
+        }
         CannotCreateShareFile(path: String) {
             description("Cannot create share file")
             display("Cannot create share file '{}'", path)
@@ -27,6 +34,22 @@ error_chain! {
             description("Cannot write share data to file")
             display("Cannot write share data to '{}'", path)
         }
+        CannotCreateCiphertextFile(path: String) {
+            description("Cannot create ciphertext file")
+            display("Cannot create ciphertext file '{}'", path)
+        }
+        CannotWriteCiphertextToFile(path: String) {
+            description("Cannot write ciphertext to file")
+            display("Cannot write ciphertext to '{}'", path)
+        }
+        CannotGenerateRandomBytes {
+            description("Cannot generate random bytes")
+            display("Cannot generate random bytes")
+        }
+        CannotEncryptSecret {
+            description("Cannot encrypt secret")
+            display("Cannot encrypt secret")
+        }
         ShareDoesNotExists(path: String) {
             description("Share does not exists")
             display("Share does not exists: '{}'", path)
@@ -43,10 +66,337 @@ error_chain! {
             description("Cannot read share")
             display("Cannot read share at '{}'", path)
         }
+        CannotParseShare(path: String) {
+            description("Cannot parse share")
+            display("Cannot parse share at '{}'", path)
+        }
+        InvalidShare(reason: String) {
+            description("Invalid share")
+            display("Invalid share: {}", reason)
+        }
+        UnknownMnemonicWord(position: usize, word: String) {
+            description("Unknown word in mnemonic share")
+            display("Word #{} '{}' is not in the word list", position, word)
+        }
+        WrongMnemonicWord(position: usize, word: String, suggestion: String) {
+            description("Wrong word in mnemonic share")
+            display("The checksum does not match, word #{} '{}' should probably be '{}'", position, word, suggestion)
+        }
+        MnemonicChecksumMismatch {
+            description("The checksum of the mnemonic share does not match")
+            display("The checksum does not match, some words are wrong")
+        }
+        SignedMnemonicShares {
+            description("Signed shares cannot be written as words")
+            display("Signed shares are too large to be written as words")
+        }
+        InvalidArmor(reason: String) {
+            description("Invalid armored share")
+            display("Invalid armored share: {}", reason)
+        }
+        ArmorChecksumMismatch {
+            description("The checksum of the armored share does not match")
+            display("The checksum of the armored share does not match, it is corrupted")
+        }
+        CorruptedShares(paths: Vec<String>) {
+            description("Shares are corrupted")
+            display("The checksum does not match in {}, the shares are corrupted", paths.join(", "))
+        }
+        InvalidSlip39Share(reason: String) {
+            description("Invalid SLIP-39 share")
+            display("Invalid SLIP-39 share: {}", reason)
+        }
+        InvalidSlip39Shares(reason: String) {
+            description("Invalid set of SLIP-39 shares")
+            display("Invalid set of SLIP-39 shares: {}", reason)
+        }
+        NotEnoughSlip39Shares(reason: String) {
+            description("Not enough SLIP-39 shares")
+            display("Not enough SLIP-39 shares: {}", reason)
+        }
+        CannotSplitSlip39(reason: String) {
+            description("Cannot split secret into SLIP-39 shares")
+            display("Cannot split secret into SLIP-39 shares: {}", reason)
+        }
+        InvalidSlip39Passphrase {
+            description("Invalid SLIP-39 passphrase")
+            display("The SLIP-39 passphrase must only contain printable ASCII characters")
+        }
+        InvalidPolicy(policy: String, reason: String) {
+            description("Invalid policy")
+            display("Invalid policy '{}': {}", policy, reason)
+        }
+        InvalidWeights(weights: String, reason: String) {
+            description("Invalid weights")
+            display("Invalid weights '{}': {}", weights, reason)
+        }
+        NotEnoughVotes(votes: u8, threshold: u8, holders: Vec<String>) {
+            description("Not enough votes to recover the secret")
+            display("Not enough votes to recover the secret, {} more needed: got {} of {} from {}",
+                    threshold - votes, votes, threshold, holders.join(", "))
+        }
+        CannotReadCustodians(path: String) {
+            description("Cannot read custodians file")
+            display("Cannot read custodians file at '{}'", path)
+        }
+        InvalidCustodians(path: String, reason: String) {
+            description("Invalid custodians file")
+            display("Invalid custodians file at '{}': {}", path, reason)
+        }
+        InvalidRecipient(key: String) {
+            description("Invalid recipient")
+            display("Invalid recipient '{}', expected an age public key such as 'age1...'", key)
+        }
+        InvalidRecipients(reason: String) {
+            description("Invalid recipients")
+            display("Invalid recipients: {}", reason)
+        }
+        CannotReadRecipients(path: String) {
+            description("Cannot read recipients file")
+            display("Cannot read recipients file at '{}'", path)
+        }
+        CannotReadIdentity(path: String) {
+            description("Cannot read identity file")
+            display("Cannot read identity file at '{}'", path)
+        }
+        InvalidIdentity(path: String) {
+            description("Invalid identity file")
+            display("Invalid identity file at '{}', expected age secret keys such as 'AGE-SECRET-KEY-1...'", path)
+        }
+        CannotEncryptShare {
+            description("Cannot encrypt share")
+            display("Cannot encrypt share")
+        }
+        ShareIsEncrypted(path: String) {
+            description("Share is encrypted")
+            display("Share '{}' is encrypted, pass the identity of its recipient with --identity", path)
+        }
+        CannotDecryptShare(path: String) {
+            description("Cannot decrypt share")
+            display("Cannot decrypt share '{}'", path)
+        }
+        NoMatchingIdentity {
+            description("No identity matches the recipient of the share")
+            display("None of the identities matches the recipient of the share")
+        }
+        InvalidEncryptedShare {
+            description("Invalid or tampered encrypted share")
+            display("Invalid or tampered encrypted share")
+        }
+        CannotReadPassphrases(path: String) {
+            description("Cannot read passphrases file")
+            display("Cannot read passphrases file at '{}'", path)
+        }
+        CannotPromptPassphrase(share: String) {
+            description("Cannot ask for passphrase")
+            display("Cannot ask for the passphrase of share {}", share)
+        }
+        PassphraseMismatch(share: String) {
+            description("Passphrases do not match")
+            display("The passphrases of share {} do not match", share)
+        }
+        MissingPassphrase(share: String) {
+            description("Missing passphrase")
+            display("No passphrase left in the passphrases file for share {}", share)
+        }
+        EmptyPassphrase {
+            description("Empty passphrase")
+            display("Shares cannot be protected by an empty passphrase")
+        }
+        CannotProtectShare {
+            description("Cannot protect share")
+            display("Cannot protect share")
+        }
+        InvalidProtectedShare {
+            description("Invalid protected share")
+            display("Invalid protected share")
+        }
+        ProtectedShareTooCostly(memory: u32, iterations: u32, parallelism: u32) {
+            description("Protected share asks for too costly a key derivation")
+            display("Protected share asks for too costly a key derivation: {} KiB of memory, {} iterations \
+                     and a parallelism of {}", memory, iterations, parallelism)
+        }
+        WrongPassphrase {
+            description("Wrong passphrase")
+            display("Wrong passphrase")
+        }
+        WrongPassphrases(paths: Vec<String>) {
+            description("Wrong passphrases")
+            display("Wrong passphrase for {}", paths.join(", "))
+        }
+        NoShareEntered {
+            description("No share was entered")
+            display("No share was entered")
+        }
+        NotEnoughShares(got: u8, threshold: u8) {
+            description("Not enough shares to recover the secret")
+            display("Not enough shares to recover the secret, got {} of {}", got, threshold)
+        }
+        CannotListShares(path: String) {
+            description("Cannot list shares")
+            display("Cannot list the shares in '{}'", path)
+        }
+        InvalidSharesPattern(pattern: String) {
+            description("Invalid pattern of share paths")
+            display("Invalid pattern of share paths '{}'", pattern)
+        }
+        NoShareFound(path: String) {
+            description("No share found")
+            display("No share found in '{}'", path)
+        }
+        PolicyUnsupported(what: String) {
+            description("Unsupported with shares split by a policy")
+            display("{} is not supported with shares split by a policy", what)
+        }
+        UnknownGroup(path: String, group: String) {
+            description("Share belongs to a group which is not part of the policy")
+            display("Share '{}' belongs to group '{}', which is not part of the policy", path, group)
+        }
+        PolicyNotMet(needed: u8, short: Vec<String>) {
+            description("Not enough groups to recover the secret")
+            display("Not enough groups to recover the secret, {} more needed out of: {}", needed, short.join(", "))
+        }
+        Slip39Unsupported(what: String) {
+            description("Unsupported with SLIP-39 shares")
+            display("{} is not supported with SLIP-39 shares", what)
+        }
+        Slip39OnlyOption(option: String) {
+            description("Option only applies to SLIP-39 shares")
+            display("{} only applies to SLIP-39 shares, use --format slip39", option)
+        }
+        CannotReadPassphrase(var: String) {
+            description("Cannot read passphrase")
+            display("Cannot read passphrase from environment variable '{}'", var)
+        }
+        CannotEncodeQrCode {
+            description("Cannot encode share as a QR code")
+            display("Cannot encode share as a QR code, large secrets should be split with --encrypt")
+        }
+        SignedSharesPrintout {
+            description("Signed shares cannot be printed")
+            display("Signed shares are too large to be printed as QR codes or cards")
+        }
+        CannotDecodeImage {
+            description("Cannot decode image")
+            display("Cannot decode image, only PNG images and SVG images written by split are supported")
+        }
+        QrCodeNotFound {
+            description("No QR code found in image")
+            display("No QR code found in image")
+        }
+        InvalidQrCode {
+            description("Cannot decode QR code")
+            display("Cannot decode QR code")
+        }
+        CannotWriteCards(path: String) {
+            description("Cannot write share cards")
+            display("Cannot write share cards to '{}'", path)
+        }
+        ShareFileCollision(path: String, first: u8, second: u8) {
+            description("Two shares would be written to the same file")
+            display("Shares #{} and #{} would both be written to '{}', the share template must tell them apart",
+                    first, second, path)
+        }
+        CannotWriteManifest(path: String) {
+            description("Cannot write manifest")
+            display("Cannot write manifest to '{}'", path)
+        }
+        ManifestAlreadyExists(path: String) {
+            description("Manifest already exists")
+            display("Manifest '{}' already exists, write the shares of another set to another directory", path)
+        }
+        ManifestOfAnotherSet(path: String) {
+            description("Manifest lists the shares of another set")
+            display("Manifest '{}' lists the shares of another set", path)
+        }
+        CannotReadManifest(path: String) {
+            description("Cannot read manifest")
+            display("Cannot read manifest at '{}'", path)
+        }
+        InvalidManifest(path: String) {
+            description("Invalid manifest")
+            display("Invalid manifest at '{}'", path)
+        }
+        SharesNotInManifest(paths: Vec<String>) {
+            description("Shares are not listed in the manifest")
+            display("Shares are not listed in the manifest: {}", paths.join(", "))
+        }
         CannotRecoverSecret {
             description("Cannot recover secret")
             display("Cannot recover secret")
         }
+        MixedShareModes(raw: Vec<String>, wrapped: Vec<String>) {
+            description("Cannot mix raw and wrapped shares")
+            display("Cannot mix raw shares ({}) with wrapped shares ({})", raw.join(", "), wrapped.join(", "))
+        }
+        MixedShareSets(sets: Vec<(String, Vec<String>)>) {
+            description("Cannot mix shares from different sets")
+            display("Cannot mix shares from different sets: {}", sets
+                .iter()
+                .map(|(set_id, paths)| format!("{} from set {}", paths.join(", "), set_id))
+                .collect::<Vec<_>>()
+                .join(", "))
+        }
+        VerificationFailed {
+            description("Share verification failed")
+            display("Share verification failed")
+        }
+        NoConsistentQuorum {
+            description("Cannot find enough consistent shares")
+            display("Cannot find enough consistent shares to recover the secret")
+        }
+        QuorumSearchGaveUp(subsets: usize, k: u8, m: usize) {
+            description("Gave up looking for consistent shares")
+            display("Gave up looking for consistent shares after {} subsets of {} out of {} shares", subsets, k, m)
+        }
+        AmbiguousQuorum(k: u8) {
+            description("Cannot tell which shares are inconsistent")
+            display("Cannot tell which shares are inconsistent, provide at least {} shares or signed shares", k + 2)
+        }
+        CannotExtendSignedShares {
+            description("Cannot extend a set of signed shares")
+            display("Cannot extend a set of signed shares, as the new shares could not be signed")
+        }
+        InconsistentShare(path: String) {
+            description("Share is inconsistent with the others")
+            display("Share '{}' is inconsistent with the others", path)
+        }
+        ShareIndexAlreadyUsed(index: u8, path: String) {
+            description("Share index is already used")
+            display("Share index {} is already used by '{}'", index, path)
+        }
+        DuplicateShareIndex(index: u8) {
+            description("Share index is given twice")
+            display("Share index {} is given twice", index)
+        }
+        NoFreeShareIndex(count: u8) {
+            description("Not enough free share indexes")
+            display("Not enough free share indexes left to generate {} shares", count)
+        }
+        ShareFileAlreadyExists(path: String) {
+            description("Share file already exists")
+            display("Share file '{}' already exists", path)
+        }
+        ConflictingCiphertexts(first: String, second: String) {
+            description("Shares refer to different ciphertexts")
+            display("Shares refer to different ciphertexts: '{}' and '{}'", first, second)
+        }
+        CiphertextNotFound(name: String) {
+            description("Cannot find ciphertext")
+            display("Cannot find ciphertext '{}' next to the shares, use --ciphertext to specify its path", name)
+        }
+        CannotReadCiphertext(path: String) {
+            description("Cannot read ciphertext")
+            display("Cannot read ciphertext at '{}'", path)
+        }
+        CannotDecryptCiphertext(path: String) {
+            description("Cannot decrypt ciphertext")
+            display("Cannot decrypt ciphertext at '{}'", path)
+        }
+        InvalidEnvelope {
+            description("Invalid or tampered ciphertext")
+            display("Invalid or tampered ciphertext")
+        }
         CannotCreateSecretFile(path: String) {
             description("Cannot write share data to file")
             display("Cannot create secret file '{}'", path)
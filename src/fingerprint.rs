@@ -0,0 +1,39 @@
+//! What may be told about a generated secret without revealing it: its fingerprint,
+//! and the public key it makes when used as a private key.
+
+use ring::signature::Ed25519KeyPair;
+use untrusted;
+
+use digest;
+use errors::*;
+
+const SEED_LEN: usize = 32;
+
+/// The hex-encoded SHA-256 digest of the secret, which tells a recovered secret apart
+/// from another one. It is unsalted, so it only hides secrets which are too long to be guessed,
+/// such as the generated ones, which hold at least 128 bits of entropy.
+pub fn fingerprint(secret: &[u8]) -> String {
+    digest::sha256_hex(secret)
+}
+
+/// The hex-encoded Ed25519 public key of the secret.
+///
+/// The secret is the seed of the private key, either as 32 bytes, or as 64 hexadecimal digits.
+pub fn ed25519_public_key(secret: &[u8]) -> Result<String> {
+    let seed = match secret.len() {
+        SEED_LEN => secret.to_vec(),
+        len if len == 2 * SEED_LEN && secret.iter().all(u8::is_ascii_hexdigit) => from_hex(secret),
+        len => bail!(ErrorKind::InvalidSeed(len)),
+    };
+
+    let pair = Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(&seed))
+        .map_err(|_| ErrorKind::InvalidSeed(secret.len()))?;
+
+    Ok(digest::to_hex(pair.public_key_bytes()))
+}
+
+fn from_hex(digits: &[u8]) -> Vec<u8> {
+    let value = |digit: u8| (digit as char).to_digit(16).unwrap() as u8;
+
+    digits.chunks(2).map(|pair| value(pair[0]) << 4 | value(pair[1])).collect()
+}
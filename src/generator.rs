@@ -0,0 +1,101 @@
+//! Kinds of random secrets which `split` may generate, such as `hex:64`:
+//!
+//! - `N` or `bytes:N`: N random bytes
+//! - `hex:N`: N random hexadecimal digits, for keys which are typed or pasted
+//! - `base58:N`: N random Base58 digits, which leave out the characters that look alike
+//! - `words:N`: a passphrase of N random words from the BIP-39 English word list
+//!
+//! Every kind must hold at least 128 bits of entropy, so that the fingerprint printed along
+//! with the secret cannot be used to guess it: 16 bytes, 32 hexadecimal digits, 22 Base58
+//! digits or 12 words.
+//!
+//! This module is also built into the build script, along with the command line parser
+//! which validates the kinds with it, so it only depends on `std`.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The largest generated secret, RustySecrets splits secrets in memory.
+const MAX_LEN: usize = 1024 * 1024;
+
+/// The least entropy of a generated secret, in bits.
+const MIN_BITS: usize = 128;
+
+/// A kind of random secret, and its length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Generator {
+    Bytes(usize),
+    Hex(usize),
+    Base58(usize),
+    Words(usize),
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Generator::Bytes(len) => write!(f, "bytes:{}", len),
+            Generator::Hex(len) => write!(f, "hex:{}", len),
+            Generator::Base58(len) => write!(f, "base58:{}", len),
+            Generator::Words(len) => write!(f, "words:{}", len),
+        }
+    }
+}
+
+impl FromStr for Generator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Generator, String> {
+        let (kind, len) = match s.find(':') {
+            Some(colon) => (&s[..colon], &s[colon + 1..]),
+            None if s.bytes().all(|c| c.is_ascii_digit()) => ("bytes", s),
+            None => return Err(format!("expected N or KIND:N, got '{}'", s)),
+        };
+
+        let len = match len.parse::<usize>() {
+            Ok(len) if (1..=MAX_LEN).contains(&len) => len,
+            _ => return Err(format!("invalid length '{}', expected 1 to {}", len, MAX_LEN)),
+        };
+
+        let generator = match kind {
+            "bytes" => Generator::Bytes(len),
+            "hex" => Generator::Hex(len),
+            "base58" => Generator::Base58(len),
+            "words" => Generator::Words(len),
+            _ => return Err(format!("unknown kind '{}', expected bytes, hex, base58 or words", kind)),
+        };
+
+        if len < generator.min_len() {
+            return Err(format!(
+                "'{}' holds less than {} bits of entropy, expected at least {}",
+                s,
+                MIN_BITS,
+                generator.with_len(generator.min_len())
+            ));
+        }
+
+        Ok(generator)
+    }
+}
+
+impl Generator {
+    /// The shortest secret of this kind which holds `MIN_BITS` of entropy.
+    fn min_len(&self) -> usize {
+        match *self {
+            Generator::Bytes(_) => MIN_BITS / 8,
+            Generator::Hex(_) => MIN_BITS / 4,
+            // Each Base58 digit holds log2(58) = 5.86 bits
+            Generator::Base58(_) => 22,
+            // The word list holds 2048 words, of 11 bits each
+            Generator::Words(_) => MIN_BITS.div_ceil(11),
+        }
+    }
+
+    fn with_len(&self, len: usize) -> Generator {
+        match *self {
+            Generator::Bytes(_) => Generator::Bytes(len),
+            Generator::Hex(_) => Generator::Hex(len),
+            Generator::Base58(_) => Generator::Base58(len),
+            Generator::Words(_) => Generator::Words(len),
+        }
+    }
+}
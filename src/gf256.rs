@@ -0,0 +1,92 @@
+//! Arithmetic in the finite fields with 256 elements used by RustySecrets and SLIP-39.
+
+pub struct Gf256 {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+impl Gf256 {
+    /// The field used by RustySecrets, ie. GF(2^8) with x^8 + x^4 + x^3 + x^2 + 1 as reduction polynomial.
+    pub fn new() -> Self {
+        Self::with_polynomial(0x1D, 2)
+    }
+
+    /// The field used by SLIP-39 and AES, ie. GF(2^8) with x^8 + x^4 + x^3 + x + 1 as reduction polynomial.
+    pub fn rijndael() -> Self {
+        Self::with_polynomial(0x1B, 3)
+    }
+
+    /// Builds the tables of the field reduced by x^8 + `poly`, out of the powers of `generator`.
+    fn with_polynomial(poly: u8, generator: u8) -> Self {
+        let mut exp = [0; 255];
+        let mut log = [0; 256];
+
+        let mut tmp: u8 = 1;
+        for (power, value) in exp.iter_mut().enumerate() {
+            *value = tmp;
+            log[tmp as usize] = power as u8;
+
+            // Multiplies by the generator, one bit at a time
+            let (mut a, mut b, mut product) = (tmp, generator, 0);
+            while b != 0 {
+                if b & 1 != 0 {
+                    product ^= a;
+                }
+                a = (a << 1) ^ if a & 0x80 != 0 { poly } else { 0 };
+                b >>= 1;
+            }
+            tmp = product;
+        }
+
+        Gf256 { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+
+        let power = (u16::from(self.log[a as usize]) + u16::from(self.log[b as usize])) % 255;
+        self.exp[power as usize]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        assert_ne!(b, 0, "Division by zero");
+
+        if a == 0 {
+            return 0;
+        }
+
+        let power = (255 + u16::from(self.log[a as usize]) - u16::from(self.log[b as usize])) % 255;
+        self.exp[power as usize]
+    }
+
+    /// Evaluates at `x` the Lagrange polynomial going through the given points,
+    /// which must have distinct x coordinates.
+    pub fn interpolate_at(&self, points: &[(u8, u8)], x: u8) -> u8 {
+        let mut sum = 0;
+
+        for (i, &(xi, yi)) in points.iter().enumerate() {
+            let mut num = 1;
+            let mut den = 1;
+
+            for (j, &(xj, _)) in points.iter().enumerate() {
+                if i != j {
+                    // Subtraction is the same as addition, ie. a XOR
+                    num = self.mul(num, x ^ xj);
+                    den = self.mul(den, xi ^ xj);
+                }
+            }
+
+            sum ^= self.mul(yi, self.div(num, den));
+        }
+
+        sum
+    }
+}
+
+impl Default for Gf256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,12 +1,35 @@
 // Adapted from
 // https://gist.github.com/ayosec/2ee0993247e003b42c5c
 
-use std::{fmt, fs, io};
+use ring::rand::{SecureRandom, SystemRandom};
+use rpassword;
 
-#[derive(Debug)]
+use generator::Generator;
+use mnemonic;
+
+use std::{env, fmt, fs, io};
+#[cfg(unix)]
+use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
+
+const HEX_DIGITS: &[u8] = b"0123456789abcdef";
+const BASE58_DIGITS: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Where to read the secret from.
+///
+/// Neither `Display` nor `Debug` show the secret held by some of the inputs,
+/// so that inputs may be part of error messages.
 pub enum Input {
     Standard(io::Stdin),
     File(fs::File, String),
+    /// The value of an environment variable, and its name
+    Env(io::Cursor<Vec<u8>>, String),
+    /// A file descriptor inherited from the calling program
+    #[cfg(unix)]
+    Fd(fs::File, RawFd),
+    /// A secret typed on the terminal
+    Prompt(io::Cursor<Vec<u8>>),
+    /// A random secret, and its kind
+    Generated(io::Cursor<Vec<u8>>, Generator),
 }
 
 impl Input {
@@ -15,7 +38,108 @@ impl Input {
     }
 
     pub fn file(path: String) -> io::Result<Input> {
-        Ok(Input::File(try!(fs::File::open(path.clone())), path))
+        Ok(Input::File(fs::File::open(path.clone())?, path))
+    }
+
+    pub fn env(var: String) -> io::Result<Input> {
+        let value = env::var(&var).map_err(|e| io::Error::new(io::ErrorKind::NotFound, e))?;
+
+        Ok(Input::Env(io::Cursor::new(value.into_bytes()), var))
+    }
+
+    /// Takes ownership of a file descriptor, which must have been inherited from the calling program.
+    #[cfg(unix)]
+    pub fn fd(fd: RawFd) -> io::Result<Input> {
+        let file = unsafe { fs::File::from_raw_fd(fd) };
+
+        // Do not close a descriptor which is not open, it could be reused by then
+        if let Err(e) = file.metadata() {
+            let _ = file.into_raw_fd();
+            return Err(e);
+        }
+
+        Ok(Input::Fd(file, fd))
+    }
+
+    /// Asks for the secret on the terminal, without echoing it, and for its confirmation.
+    pub fn prompt() -> io::Result<Input> {
+        let secret = rpassword::prompt_password("Secret: ")?;
+
+        if secret.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the secret is empty"));
+        }
+
+        if rpassword::prompt_password("Confirm secret: ")? != secret {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the secrets do not match"));
+        }
+
+        Ok(Input::Prompt(io::Cursor::new(secret.into_bytes())))
+    }
+
+    /// Generates a random secret of the given kind.
+    pub fn generate(generator: Generator) -> io::Result<Input> {
+        let rng = SystemRandom::new();
+        let random = |len: usize| {
+            let mut bytes = vec![0; len];
+            rng.fill(&mut bytes)
+                .map(|_| bytes)
+                .map_err(|_| io::Error::other("cannot generate random bytes"))
+        };
+
+        let secret = match generator {
+            Generator::Bytes(len) => random(len)?,
+            // Each random byte picks a digit, or a word out of two bytes, which the word list evenly divides
+            Generator::Hex(len) => random(len)?
+                .iter()
+                .map(|byte| HEX_DIGITS[usize::from(byte & 0xf)])
+                .collect(),
+            // Bytes past the last multiple of 58 are dropped, so that every digit is as likely
+            Generator::Base58(len) => {
+                let mut digits = Vec::with_capacity(len);
+                while digits.len() < len {
+                    digits.extend(
+                        random(len - digits.len())?
+                            .iter()
+                            .filter(|&&byte| usize::from(byte) < 256 / 58 * 58)
+                            .map(|&byte| BASE58_DIGITS[usize::from(byte) % 58]),
+                    );
+                }
+                digits
+            }
+            Generator::Words(len) => random(2 * len)?
+                .chunks(2)
+                .map(|pair| mnemonic::word(usize::from(pair[0]) << 8 | usize::from(pair[1])))
+                .collect::<Vec<_>>()
+                .join(" ")
+                .into_bytes(),
+        };
+
+        Ok(Input::Generated(io::Cursor::new(secret), generator))
+    }
+
+    /// Returns the random secret, if the input was generated, to derive its fingerprint from.
+    pub fn generated(&self) -> Option<&[u8]> {
+        match *self {
+            Input::Generated(ref secret, _) => Some(secret.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of the input, if it is known in advance.
+    pub fn size(&self) -> Option<u64> {
+        match *self {
+            Input::Standard(_) => None,
+            Input::File(ref f, _) => f.metadata().ok().map(|metadata| metadata.len()),
+            #[cfg(unix)]
+            Input::Fd(ref f, _) => f
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len()),
+            Input::Env(ref secret, _) | Input::Prompt(ref secret) | Input::Generated(ref secret, _) => {
+                Some(secret.get_ref().len() as u64)
+            }
+        }
     }
 }
 
@@ -24,6 +148,11 @@ impl io::Read for Input {
         match *self {
             Input::Standard(ref mut s) => s.read(buf),
             Input::File(ref mut f, _) => f.read(buf),
+            #[cfg(unix)]
+            Input::Fd(ref mut f, _) => f.read(buf),
+            Input::Env(ref mut secret, _) | Input::Prompt(ref mut secret) | Input::Generated(ref mut secret, _) => {
+                secret.read(buf)
+            }
         }
     }
 }
@@ -33,6 +162,17 @@ impl fmt::Display for Input {
         match *self {
             Input::Standard(_) => write!(f, "<stdin>"),
             Input::File(_, ref path) => write!(f, "{}", path),
+            Input::Env(_, ref var) => write!(f, "${}", var),
+            #[cfg(unix)]
+            Input::Fd(_, fd) => write!(f, "<fd {}>", fd),
+            Input::Prompt(_) => write!(f, "<terminal>"),
+            Input::Generated(_, generator) => write!(f, "<random {}>", generator),
         }
     }
 }
+
+impl fmt::Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Input({})", self)
+    }
+}
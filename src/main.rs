@@ -6,15 +6,33 @@
 extern crate colored;
 use colored::*;
 
+extern crate age as age_crypto;
+extern crate argon2;
+extern crate base64;
+extern crate chrono;
 extern crate clap;
 
 #[macro_use]
 extern crate error_chain;
+extern crate font8x8;
+extern crate glob;
 
 #[macro_use]
 extern crate log;
 extern crate mime;
+extern crate png;
+extern crate protobuf;
+extern crate qrcode;
+extern crate ring;
+extern crate rpassword;
+extern crate rqrr;
 extern crate rusty_secrets;
+extern crate serde;
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate untrusted;
 
 mod errors;
 use errors::*;
@@ -22,12 +40,38 @@ use errors::*;
 mod input;
 use input::Input;
 
+mod share;
+use share::{Format, Mode};
+use template::Template;
+
+mod age;
+mod armor;
+mod cards;
 mod cli;
 mod cmds;
+mod custodians;
+mod digest;
+mod envelope;
+mod fingerprint;
+mod generator;
+mod gf256;
+mod manifest;
+mod mnemonic;
+mod pdf;
+mod policy;
+mod progress;
+mod protect;
+mod qr;
+mod quorum;
+mod recipients;
+mod slip39;
+mod template;
 mod version;
+mod weights;
 mod logger;
 use logger::ColoredTermLogger;
 
+use clap::ArgMatches;
 use log::{Level, LevelFilter};
 use std::path::Path;
 
@@ -43,7 +87,13 @@ fn main() {
             error!("{} {:?}", "backtrace:".blue().bold(), backtrace);
         }
 
-        ::std::process::exit(1);
+        // Let scripts tell a share set which does not verify apart from other failures
+        let code = match *e.kind() {
+            ErrorKind::VerificationFailed => 2,
+            _ => 1,
+        };
+
+        ::std::process::exit(code);
     }
 }
 
@@ -54,6 +104,10 @@ fn run() -> Result<()> {
     let verbose = matches
         .subcommand_matches("split")
         .or_else(|| matches.subcommand_matches("recover"))
+        .or_else(|| matches.subcommand_matches("inspect"))
+        .or_else(|| matches.subcommand_matches("verify"))
+        .or_else(|| matches.subcommand_matches("reshare"))
+        .or_else(|| matches.subcommand_matches("extend"))
         .map(|matches| matches.is_present("verbose"))
         .unwrap_or(false);
 
@@ -64,44 +118,302 @@ fn run() -> Result<()> {
     log::set_boxed_logger(Box::new(logger)).unwrap();
 
     if let Some(matches) = matches.subcommand_matches("split") {
-        let secret_arg = matches.value_of("INPUT").unwrap();
-        let secret_input = if secret_arg == "-" {
-            Input::stdin()
-        } else {
-            Input::file(secret_arg.to_string()).chain_err(|| ErrorKind::CannotOpenSecretFile(secret_arg.to_string()))?
+        let secret_input = secret_input(matches)?;
+
+        // Nobody sees a generated secret, only what may be told about it without revealing it
+        let generated = match secret_input.generated() {
+            Some(secret) => Some((
+                fingerprint::fingerprint(secret),
+                match matches.value_of("public-key") {
+                    Some(_) => Some(fingerprint::ed25519_public_key(secret)?),
+                    None => None,
+                },
+            )),
+            None => None,
         };
 
         let output_path = Path::new(matches.value_of("DIR").unwrap());
-        let k = matches.value_of("k").unwrap().parse::<u8>().unwrap();
-        let n = matches.value_of("n").unwrap().parse::<u8>().unwrap();
+        let groups: Vec<(u8, u8)> = matches
+            .values_of("group")
+            .map_or_else(Vec::new, |values| values.map(parse_group).collect());
+        let policy = match matches.value_of("policy") {
+            Some(policy) => Some(policy.parse::<policy::Policy>()?),
+            None => None,
+        };
+        let holders = match matches.value_of("weights") {
+            Some(weights) => Some(weights::parse(weights)?),
+            None => None,
+        };
+        let custodians = match matches.value_of("custodians") {
+            Some(path) => Some(custodians::load(Path::new(path))?),
+            None => None,
+        };
+        let (k, n) = match (groups.first(), &policy) {
+            (_, Some(policy)) => (policy.threshold, policy.groups.len() as u8),
+            (Some(&group), _) if groups.len() == 1 => group,
+            (Some(_), _) => (0, 0),
+            (None, None) => (
+                matches.value_of("k").unwrap().parse::<u8>().unwrap(),
+                match (&holders, &custodians) {
+                    (Some(holders), _) => weights::total(holders),
+                    (None, Some(custodians)) => custodians.len() as u8,
+                    (None, None) => matches.value_of("n").unwrap().parse::<u8>().unwrap(),
+                },
+            ),
+        };
         let mime_type = matches.value_of("MIME").map(|v| v.parse().unwrap());
         let sign_shares = matches.is_present("sign");
         let raw = matches.is_present("raw");
-        let share_tmpl = matches.value_of("share-tmpl").unwrap_or("share_{{num}}");
+        let share_tmpl = matches
+            .value_of("share-tmpl")
+            .unwrap_or("share_{{num}}")
+            .parse::<Template>()
+            .unwrap();
+        let ciphertext_path = matches.value_of("encrypt").map(Path::new);
+        let format = matches
+            .value_of("format")
+            .map_or(Format::Text, |format| format.parse().unwrap());
+
+        let slip39_params = if format == Format::Slip39 {
+            Some(slip39::Params {
+                group_threshold: matches
+                    .value_of("group-threshold")
+                    .map_or(1, |v| v.parse::<u8>().unwrap()),
+                groups: if groups.is_empty() { vec![(k, n)] } else { groups },
+                iteration_exponent: matches
+                    .value_of("iteration-exponent")
+                    .map_or(1, |v| v.parse::<u8>().unwrap()),
+                passphrase: passphrase(matches)?,
+            })
+        } else {
+            for option in &["group", "iteration-exponent", "passphrase-env"] {
+                if matches.is_present(option) {
+                    bail!(ErrorKind::Slip39OnlyOption(format!("--{}", option)));
+                }
+            }
+            None
+        };
+
+        let mut recipients = match matches.values_of("recipient") {
+            Some(values) => values.map(recipients::parse).collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        if let Some(path) = matches.value_of("recipients-file") {
+            recipients.extend(recipients::load(Path::new(path))?);
+        }
+
+        let protection = cmds::Protection {
+            recipients,
+            passphrases: if matches.is_present("passphrase-protect") {
+                Some(share_passphrases(matches)?)
+            } else {
+                None
+            },
+        };
+
+        let printouts = cmds::Printouts {
+            qr: matches.value_of("qr").map(|format| format.parse().unwrap()),
+            qr_only: matches.is_present("qr-only"),
+            pdf: matches.value_of("print-pdf").map(Path::new),
+            label: matches.value_of("label").map(String::from),
+        };
 
-        cmds::split(
-            secret_input,
-            output_path,
+        let options = cmds::SplitOptions {
             k,
             n,
             mime_type,
             sign_shares,
             raw,
-            share_tmpl,
-        )?
+            share_tmpl: &share_tmpl,
+            ciphertext_path,
+            format,
+            slip39_params,
+            policy,
+            holders,
+            custodians,
+            protection,
+            printouts,
+        };
+
+        cmds::split(secret_input, output_path, options)?;
+
+        if let Some((fingerprint, public_key)) = generated {
+            println!("Fingerprint: {}", fingerprint);
+            if let Some(public_key) = public_key {
+                println!("Ed25519 public key: {}", public_key);
+            }
+        }
     } else if let Some(matches) = matches.subcommand_matches("recover") {
+        let shares = matches
+            .values_of("SHARES")
+            .map_or_else(Vec::new, |values| values.map(Path::new).collect());
+
+        let passphrase = passphrase(matches)?;
+        let keys = keys(matches)?;
+
+        let options = cmds::RecoverOptions {
+            output_path: matches.value_of("FILE").map(Path::new),
+            verify_signatures: matches.is_present("verify"),
+            mode: share_mode(matches),
+            find_bad: matches.is_present("find-bad"),
+            ciphertext_path: matches.value_of("ciphertext").map(Path::new),
+            passphrase: &passphrase,
+            manifest_path: matches.value_of("manifest").map(Path::new),
+            keys: &keys,
+            interactive: matches.is_present("interactive"),
+        };
+
+        cmds::recover(shares, options)?
+    } else if let Some(matches) = matches.subcommand_matches("inspect") {
         let shares = matches
             .values_of("SHARES")
             .unwrap()
             .map(Path::new)
             .collect();
 
-        let output_path = matches.value_of("FILE").map(Path::new);
-        let verify_signatures = matches.is_present("verify");
-        let raw = matches.is_present("raw");
+        let keys = keys(matches)?;
+
+        cmds::inspect(shares, &keys)?
+    } else if let Some(matches) = matches.subcommand_matches("verify") {
+        let shares = matches
+            .values_of("SHARES")
+            .unwrap()
+            .map(Path::new)
+            .collect();
+
+        let mode = share_mode(matches);
+        let ciphertext_path = matches.value_of("ciphertext").map(Path::new);
+
+        let passphrase = passphrase(matches)?;
+        let keys = keys(matches)?;
+
+        cmds::verify(shares, mode, ciphertext_path, &passphrase, &keys)?
+    } else if let Some(matches) = matches.subcommand_matches("reshare") {
+        let shares = matches
+            .values_of("SHARES")
+            .unwrap()
+            .map(Path::new)
+            .collect();
+
+        let output_path = Path::new(matches.value_of("DIR").unwrap());
+        let k = matches.value_of("k").unwrap().parse::<u8>().unwrap();
+        let n = matches.value_of("n").unwrap().parse::<u8>().unwrap();
+        let sign_shares = matches.is_present("sign");
+        let share_tmpl = matches
+            .value_of("share-tmpl")
+            .unwrap_or("share_{{num}}")
+            .parse::<Template>()
+            .unwrap();
+        let keys = keys(matches)?;
 
-        cmds::recover(shares, output_path, verify_signatures, raw)?
+        cmds::reshare(shares, output_path, k, n, sign_shares, &share_tmpl, &keys)?
+    } else if let Some(matches) = matches.subcommand_matches("extend") {
+        let shares = matches
+            .values_of("SHARES")
+            .unwrap()
+            .map(Path::new)
+            .collect();
+
+        let output_path = Path::new(matches.value_of("DIR").unwrap());
+        let share_tmpl = matches
+            .value_of("share-tmpl")
+            .unwrap_or("share_{{num}}")
+            .parse::<Template>()
+            .unwrap();
+        let indexes = matches
+            .values_of("index")
+            .map(|values| values.map(|v| v.parse::<u8>().unwrap()).collect());
+        let count = matches
+            .value_of("count")
+            .map_or(1, |v| v.parse::<u8>().unwrap());
+        let keys = keys(matches)?;
+
+        cmds::extend(shares, output_path, &share_tmpl, indexes, count, &keys)?
     }
 
     Ok(())
 }
+
+fn share_mode(matches: &ArgMatches) -> Option<Mode> {
+    if matches.is_present("raw") {
+        Some(Mode::Raw)
+    } else if matches.is_present("wrapped") {
+        Some(Mode::Wrapped)
+    } else {
+        None
+    }
+}
+
+fn parse_group(value: &str) -> (u8, u8) {
+    let mut parts = value.split('/').map(|part| part.parse::<u8>().unwrap());
+    (parts.next().unwrap(), parts.next().unwrap())
+}
+
+/// Reads the SLIP-39 passphrase out of the environment variable given with `--passphrase-env`.
+fn passphrase(matches: &ArgMatches) -> Result<String> {
+    match matches.value_of("passphrase-env") {
+        Some(var) => ::std::env::var(var).chain_err(|| ErrorKind::CannotReadPassphrase(var.to_string())),
+        None => Ok(String::new()),
+    }
+}
+
+/// Reads the keys to decrypt the shares with: the identities held by the files given with `--identity`,
+/// and the passphrases of protected shares.
+fn keys(matches: &ArgMatches) -> Result<cmds::Keys> {
+    let mut identities = Vec::new();
+
+    for path in matches.values_of("identity").into_iter().flatten() {
+        identities.extend(age::Identity::load(Path::new(path))?);
+    }
+
+    Ok(cmds::Keys {
+        identities,
+        passphrases: share_passphrases(matches)?,
+    })
+}
+
+/// Where to get the passphrases of protected shares from, which are asked for unless
+/// given with `--share-passphrases` or `--share-passphrase-env`.
+fn share_passphrases(matches: &ArgMatches) -> Result<protect::Passphrases> {
+    match (matches.value_of("share-passphrases"), matches.value_of("share-passphrase-env")) {
+        (Some(path), _) => protect::Passphrases::from_file(Path::new(path)),
+        (None, Some(var)) => protect::Passphrases::from_env(var),
+        (None, None) => Ok(protect::Passphrases::Prompt),
+    }
+}
+
+/// Where to read the secret to split from: the `INPUT` file or stdin, an environment variable,
+/// an inherited file descriptor, the terminal, or a random secret.
+fn secret_input(matches: &ArgMatches) -> Result<Input> {
+    if let Some(var) = matches.value_of("secret-env") {
+        return Input::env(var.to_string()).chain_err(|| ErrorKind::CannotOpenSecret(format!("${}", var)));
+    }
+
+    if let Some(fd) = matches.value_of("secret-fd") {
+        return secret_fd(fd.parse().unwrap()).chain_err(|| ErrorKind::CannotOpenSecret(format!("<fd {}>", fd)));
+    }
+
+    if matches.is_present("secret-prompt") {
+        return Input::prompt().chain_err(|| ErrorKind::CannotOpenSecret("<terminal>".to_string()));
+    }
+
+    if let Some(generator) = matches.value_of("generate") {
+        return Input::generate(generator.parse().unwrap())
+            .chain_err(|| ErrorKind::CannotOpenSecret(format!("<random {}>", generator)));
+    }
+
+    match matches.value_of("INPUT").unwrap() {
+        "-" => Ok(Input::stdin()),
+        path => Input::file(path.to_string()).chain_err(|| ErrorKind::CannotOpenSecretFile(path.to_string())),
+    }
+}
+
+#[cfg(unix)]
+fn secret_fd(fd: i32) -> ::std::io::Result<Input> {
+    Input::fd(fd)
+}
+
+#[cfg(not(unix))]
+fn secret_fd(_fd: i32) -> ::std::io::Result<Input> {
+    Err(::std::io::Error::other("file descriptors are only supported on Unix"))
+}
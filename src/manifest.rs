@@ -0,0 +1,171 @@
+//! The manifest written by `split` next to the shares, recording what was produced:
+//!
+//! ```text
+//! {
+//!   "set_id": "9f2c41d0",
+//!   "k": 2,
+//!   "n": 3,
+//!   "mime_type": "text/plain",
+//!   "created": "2018-03-02T14:12:05Z",
+//!   "version": "0.2.1",
+//!   "shares": [
+//!     {
+//!       "file": "share_0",
+//!       "sha256": "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+//!     },
+//!     ...
+//!   ]
+//! }
+//! ```
+//!
+//! Shares are hashed without their headers, so that a share still matches the manifest
+//! whichever format it is written in, or if it was scanned back from a QR code.
+//!
+//! RustySecrets signs the shares with a one-time key which it does not keep, so the manifest
+//! cannot be signed with it. It records the root of the Merkle tree the shares were signed
+//! with instead, which is the public key checked by `recover --verify`.
+
+use chrono::{SecondsFormat, Utc};
+use serde_json;
+
+use digest;
+use errors::*;
+use share::Share;
+use version;
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest file, in the directory of the shares.
+pub const FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The identifier of the set, as held by the shares
+    pub set_id: Option<String>,
+    /// The number of shares, or of SLIP-39 groups, necessary to recover the secret
+    pub k: u8,
+    /// The number of shares, or of SLIP-39 groups
+    pub n: u8,
+    /// The threshold and number of shares of each SLIP-39 group, if several
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<(u8, u8)>,
+    pub mime_type: Option<String>,
+    /// When the shares were generated, in RFC 3339 format
+    pub created: String,
+    /// The version of the tool which generated the shares
+    pub version: String,
+    /// The hex-encoded root of the Merkle tree the shares were signed with, if signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_root: Option<String>,
+    pub shares: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// The name of the share file
+    pub file: String,
+    /// The hex-encoded SHA-256 digest of the share, without its headers
+    pub sha256: String,
+    /// The name of the custodian of the share, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custodian: Option<String>,
+}
+
+impl Manifest {
+    /// The manifest of a new set of shares, written to the given files.
+    pub fn new(
+        shares: &[Share],
+        k: u8,
+        n: u8,
+        groups: Vec<(u8, u8)>,
+        mime_type: Option<String>,
+        files: Vec<PathBuf>,
+    ) -> Manifest {
+        Manifest {
+            set_id: shares.first().and_then(Share::set_id),
+            k,
+            n,
+            groups,
+            mime_type,
+            created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            version: version::get().to_string(),
+            signature_root: shares.first().and_then(Share::signature_root),
+            shares: entries(shares, files),
+        }
+    }
+
+    /// Lists the shares added to the set, written to the given files.
+    pub fn add(&mut self, shares: &[Share], files: Vec<PathBuf>) {
+        self.shares.extend(entries(shares, files));
+    }
+
+    pub fn load(path: &Path) -> Result<Manifest> {
+        let content =
+            fs::read_to_string(path).chain_err(|| ErrorKind::CannotReadManifest(format!("{}", path.display())))?;
+
+        serde_json::from_str(&content).chain_err(|| ErrorKind::InvalidManifest(format!("{}", path.display())))
+    }
+
+    /// Writes the manifest of a new set, which must not replace the manifest of another one.
+    pub fn create(&self, path: &Path) -> Result<()> {
+        let file = OpenOptions::new().write(true).create_new(true).open(path);
+        if file.as_ref().err().map(io::Error::kind) == Some(io::ErrorKind::AlreadyExists) {
+            bail!(ErrorKind::ManifestAlreadyExists(format!("{}", path.display())));
+        }
+
+        let json = self.to_json(path)?;
+
+        file.and_then(|mut file| file.write_all(json.as_bytes()))
+            .chain_err(|| ErrorKind::CannotWriteManifest(format!("{}", path.display())))
+    }
+
+    /// Writes the manifest over its previous version, once shares were added to the set.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = self.to_json(path)?;
+
+        fs::write(path, json).chain_err(|| ErrorKind::CannotWriteManifest(format!("{}", path.display())))
+    }
+
+    fn to_json(&self, path: &Path) -> Result<String> {
+        let mut json = serde_json::to_string_pretty(self)
+            .chain_err(|| ErrorKind::CannotWriteManifest(format!("{}", path.display())))?;
+        json.push('\n');
+
+        Ok(json)
+    }
+
+    /// The entry of the given share, if it is part of the set.
+    pub fn entry(&self, share: &Share) -> Option<&Entry> {
+        let hash = hash(share);
+
+        self.shares.iter().find(|entry| entry.sha256 == hash)
+    }
+}
+
+/// Fails if there is already a manifest at the given path, to check before writing any share.
+pub fn check_absent(path: &Path) -> Result<()> {
+    if path.exists() {
+        bail!(ErrorKind::ManifestAlreadyExists(format!("{}", path.display())));
+    }
+
+    Ok(())
+}
+
+fn entries(shares: &[Share], files: Vec<PathBuf>) -> Vec<Entry> {
+    shares
+        .iter()
+        .zip(files)
+        .map(|(share, file)| Entry {
+            file: file.to_string_lossy().into_owned(),
+            sha256: hash(share),
+            custodian: share.header("Custodian").map(String::from),
+        })
+        .collect()
+}
+
+/// The digest of a share recorded in the manifest.
+pub fn hash(share: &Share) -> String {
+    digest::sha256_hex(share.as_str().as_bytes())
+}
@@ -0,0 +1,249 @@
+//! Encoding of shares as sequences of words, which are easier to write down and type back than base64.
+//!
+//! The bytes are followed by the first 4 bytes of their SHA-256 digest, and the resulting bits
+//! are split into groups of 11, each of them picking a word out of the 2048 words of the
+//! BIP-39 English word list. The bits of the last word are padded with zeros.
+//!
+//! The first 4 letters of every word are unique, so words may be abbreviated to them.
+
+use ring::digest::{self, SHA256};
+
+use errors::*;
+
+const WORD_LIST: &str = include_str!("english.txt");
+const BITS_PER_WORD: usize = 11;
+const CHECKSUM_LEN: usize = 4;
+const PREFIX_LEN: usize = 4;
+
+/// Above this many words, looking for a single wrong word would take too long.
+const MAX_WORDS_TO_CORRECT: usize = 256;
+
+fn word_list() -> Vec<&'static str> {
+    WORD_LIST.lines().collect()
+}
+
+/// The word at the given index of the word list, wrapping around its 2048 words.
+pub fn word(index: usize) -> &'static str {
+    let words = word_list();
+    words[index % words.len()]
+}
+
+/// Whether the given word is in the word list.
+pub fn is_word(word: &str) -> bool {
+    find_word(&word_list(), word).is_some()
+}
+
+/// Encodes the given bytes as a sequence of words.
+pub fn encode(bytes: &[u8]) -> Vec<&'static str> {
+    let words = word_list();
+
+    let mut data = bytes.to_vec();
+    data.extend_from_slice(&checksum(bytes));
+
+    let bits = data.len() * 8;
+    let padding = (BITS_PER_WORD - bits % BITS_PER_WORD) % BITS_PER_WORD;
+    let count = (bits + padding) / BITS_PER_WORD;
+
+    (0..count)
+        .map(|i| {
+            let index = (0..BITS_PER_WORD).fold(0, |index, j| {
+                let bit = i * BITS_PER_WORD + j;
+                let set = bit < bits && data[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                (index << 1) | usize::from(set)
+            });
+            words[index]
+        })
+        .collect()
+}
+
+/// Decodes a sequence of words, pointing at the wrong word if the checksum does not match.
+pub fn decode(words: &[&str]) -> Result<Vec<u8>> {
+    let list = word_list();
+
+    let mut indexes = Vec::with_capacity(words.len());
+
+    for (position, word) in words.iter().enumerate() {
+        match find_word(&list, word) {
+            Some(index) => indexes.push(index),
+            None => bail!(ErrorKind::UnknownMnemonicWord(position + 1, word.to_string())),
+        }
+    }
+
+    if let Some(bytes) = decode_indexes(&indexes) {
+        return Ok(bytes);
+    }
+
+    if let Some((position, index)) = find_wrong_word(&indexes) {
+        bail!(ErrorKind::WrongMnemonicWord(
+            position + 1,
+            words[position].to_string(),
+            list[index].to_string()
+        ));
+    }
+
+    bail!(ErrorKind::MnemonicChecksumMismatch)
+}
+
+/// Looks a word up, either in full or by its first letters.
+fn find_word(list: &[&str], word: &str) -> Option<usize> {
+    let word = word.to_lowercase();
+
+    if let Ok(index) = list.binary_search(&word.as_str()) {
+        return Some(index);
+    }
+
+    if word.len() < PREFIX_LEN {
+        return None;
+    }
+
+    list.iter().position(|candidate| candidate.starts_with(&word))
+}
+
+/// Returns the bytes encoded by the given word indexes if their checksum matches.
+fn decode_indexes(indexes: &[usize]) -> Option<Vec<u8>> {
+    check(&pack(indexes), indexes.len() * BITS_PER_WORD)
+}
+
+/// Packs the bits of the word indexes, including the padding.
+fn pack(indexes: &[usize]) -> Vec<u8> {
+    let bits = indexes.len() * BITS_PER_WORD;
+    let mut data = vec![0; bits / 8 + 1];
+
+    for (position, &index) in indexes.iter().enumerate() {
+        set_word(&mut data, position, index);
+    }
+
+    data
+}
+
+fn set_word(data: &mut [u8], position: usize, index: usize) {
+    for j in 0..BITS_PER_WORD {
+        let bit = position * BITS_PER_WORD + j;
+        let mask = 0x80 >> (bit % 8);
+
+        if index & (1 << (BITS_PER_WORD - 1 - j)) != 0 {
+            data[bit / 8] |= mask;
+        } else {
+            data[bit / 8] &= !mask;
+        }
+    }
+}
+
+/// Splits the packed bits into bytes and checksum, and returns the bytes if the checksum matches.
+fn check(data: &[u8], bits: usize) -> Option<Vec<u8>> {
+    // The padding may span more than a byte, so every length it allows is tried
+    (0..bits / 8 + 1)
+        .rev()
+        .take_while(|len| bits - 8 * len < BITS_PER_WORD)
+        .find_map(|len| {
+            if len < CHECKSUM_LEN || data[len..].iter().any(|&byte| byte != 0) {
+                return None;
+            }
+
+            let (bytes, sum) = data[..len].split_at(len - CHECKSUM_LEN);
+            if checksum(bytes) == sum {
+                Some(bytes.to_vec())
+            } else {
+                None
+            }
+        })
+}
+
+/// Looks for the only word which, once replaced, makes the checksum match.
+fn find_wrong_word(indexes: &[usize]) -> Option<(usize, usize)> {
+    if indexes.len() > MAX_WORDS_TO_CORRECT {
+        return None;
+    }
+
+    let bits = indexes.len() * BITS_PER_WORD;
+    let mut data = pack(indexes);
+    let mut found = None;
+
+    for (position, &original) in indexes.iter().enumerate() {
+        for candidate in (0..1 << BITS_PER_WORD).filter(|&candidate| candidate != original) {
+            set_word(&mut data, position, candidate);
+
+            if check(&data, bits).is_some() {
+                if found.is_some() {
+                    return None;
+                }
+                found = Some((position, candidate));
+            }
+        }
+
+        set_word(&mut data, position, original);
+    }
+
+    found
+}
+
+fn checksum(bytes: &[u8]) -> Vec<u8> {
+    digest::digest(&SHA256, bytes).as_ref()[..CHECKSUM_LEN].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for len in 0..40 {
+            let bytes = (0..len).map(|i| (i * 37) as u8).collect::<Vec<_>>();
+            let words = encode(&bytes);
+
+            assert_eq!(decode(&words).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn abbreviations() {
+        let words = encode(b"some share data");
+        let abbreviated = words.iter().map(|word| &word[..word.len().min(PREFIX_LEN)]).collect::<Vec<_>>();
+        let upper = words.iter().map(|word| word.to_uppercase()).collect::<Vec<_>>();
+
+        assert_eq!(decode(&abbreviated).unwrap(), b"some share data");
+        assert_eq!(decode(&upper.iter().map(String::as_str).collect::<Vec<_>>()).unwrap(), b"some share data");
+    }
+
+    #[test]
+    fn unknown_word() {
+        let mut words = encode(b"some share data");
+        words[2] = "notaword";
+
+        match decode(&words) {
+            Err(Error(ErrorKind::UnknownMnemonicWord(3, ref word), _)) if word == "notaword" => {}
+            _ => panic!("expected an unknown word"),
+        }
+    }
+
+    #[test]
+    fn wrong_word() {
+        let words = encode(b"some share data");
+        let original = words[4];
+        let mut typo = words.clone();
+        typo[4] = if original == word(0) { word(1) } else { word(0) };
+
+        match decode(&typo) {
+            Err(Error(ErrorKind::WrongMnemonicWord(5, _, ref suggestion), _)) => assert_eq!(suggestion, original),
+            _ => panic!("expected the wrong word to be found"),
+        }
+    }
+
+    #[test]
+    fn bad_checksum() {
+        // Swapped words are caught by the checksum
+        let mut words = encode(b"some share data");
+        let last = words.len() - 1;
+        words.swap(0, last);
+
+        assert!(decode(&words).is_err());
+        assert!(decode(&[]).is_err());
+    }
+
+    #[test]
+    fn word_wraps_around() {
+        assert_eq!(word(0), "abandon");
+        assert_eq!(word(2047), "zoo");
+        assert_eq!(word(2048), "abandon");
+    }
+}
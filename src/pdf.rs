@@ -0,0 +1,140 @@
+//! A minimal PDF writer, enough to lay out text and filled rectangles on A4 pages.
+//!
+//! Only the standard Helvetica and Courier fonts are used, so that nothing needs embedding,
+//! and text outside of printable ASCII is replaced with '?'.
+
+use std::fmt::Write;
+
+/// Width of an A4 page, in points.
+pub const PAGE_WIDTH: f64 = 595.0;
+/// Height of an A4 page, in points.
+pub const PAGE_HEIGHT: f64 = 842.0;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "/F1",
+            Font::Bold => "/F2",
+            Font::Mono => "/F3",
+        }
+    }
+}
+
+const FONTS: [&str; 3] = ["Helvetica", "Helvetica-Bold", "Courier"];
+
+/// A page being drawn, in points from the bottom left corner.
+#[derive(Default)]
+pub struct Page {
+    content: String,
+}
+
+impl Page {
+    pub fn new() -> Page {
+        Page::default()
+    }
+
+    pub fn text(&mut self, x: f64, y: f64, font: Font, size: f64, text: &str) {
+        let _ = writeln!(
+            self.content,
+            "BT {} {:.2} Tf {:.2} {:.2} Td ({}) Tj ET",
+            font.resource(),
+            size,
+            x,
+            y,
+            escape(text)
+        );
+    }
+
+    pub fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        let _ = writeln!(self.content, "{:.2} {:.2} {:.2} {:.2} re f", x, y, width, height);
+    }
+
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        let _ = writeln!(self.content, "0.5 w {:.2} {:.2} m {:.2} {:.2} l S", x1, y1, x2, y2);
+    }
+}
+
+/// Escapes the text of a string object.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+/// Writes the pages as a PDF document.
+pub fn write(pages: &[Page]) -> Vec<u8> {
+    // Objects are numbered from 1: the catalog, the page tree, the fonts, then a page and its content for each page
+    let first_page = 3 + FONTS.len();
+    let mut objects = Vec::new();
+
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+
+    let kids = (0..pages.len())
+        .map(|i| format!("{} 0 R", first_page + 2 * i))
+        .collect::<Vec<_>>();
+    objects.push(format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    ));
+
+    for font in &FONTS {
+        objects.push(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            font
+        ));
+    }
+
+    let fonts = (0..FONTS.len())
+        .map(|i| format!("/F{} {} 0 R", i + 1, 3 + i))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    for (i, page) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {} >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            fonts,
+            first_page + 2 * i + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            page.content.len(),
+            page.content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+    }
+
+    let xref = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+
+    pdf.into_bytes()
+}
@@ -0,0 +1,173 @@
+//! Recovery policies, which split the secret between groups of custodians.
+//!
+//! A policy such as `2 of (exec:2/3, eng:3/5)` first splits the secret into one share per
+//! group, 2 of which are needed to recover it, then splits the share of each group between
+//! its members: 2 of the 3 shares of `exec` recover the share of `exec`, and 3 of the 5 shares
+//! of `eng` recover the share of `eng`.
+//!
+//! The shares of the members are raw shares of the share of their group, but their `Mode`
+//! header tells how to unwrap the secret itself. They also carry the name of their group in
+//! a `Group` header, and the whole policy in a `Policy` header, so that `recover` can tell
+//! which groups are still short.
+
+use errors::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Policy {
+    /// The number of groups necessary to recover the secret
+    pub threshold: u8,
+    pub groups: Vec<Group>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    /// The name of the group, which is also the name of the directory of its shares
+    pub name: String,
+    /// The number of shares of the group necessary to recover its share
+    pub k: u8,
+    /// The number of shares of the group
+    pub n: u8,
+}
+
+impl Policy {
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let groups = self
+            .groups
+            .iter()
+            .map(|group| format!("{}:{}/{}", group.name, group.k, group.n))
+            .collect::<Vec<_>>();
+
+        write!(f, "{} of ({})", self.threshold, groups.join(", "))
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Policy> {
+        let invalid = |reason: &str| ErrorKind::InvalidPolicy(s.to_string(), reason.to_string());
+
+        let (threshold, groups) = match s.find(" of ") {
+            Some(of) => (&s[..of], s[of + 4..].trim()),
+            None => bail!(invalid("expected 'T of (NAME:K/N, ...)'")),
+        };
+
+        let threshold = threshold
+            .trim()
+            .parse::<u8>()
+            .chain_err(|| invalid("invalid number of groups"))?;
+
+        if !groups.starts_with('(') || !groups.ends_with(')') {
+            bail!(invalid("the groups must be enclosed in parentheses"));
+        }
+
+        let groups = groups[1..groups.len() - 1]
+            .split(',')
+            .map(|group| parse_group(group.trim()).chain_err(|| invalid(&format!("invalid group '{}'", group.trim()))))
+            .collect::<Result<Vec<_>>>()?;
+
+        if threshold == 0 || groups.len() > usize::from(u8::MAX) || usize::from(threshold) > groups.len() {
+            bail!(invalid("the number of groups needed must be between 1 and the number of groups"));
+        }
+
+        for (i, group) in groups.iter().enumerate() {
+            if groups[..i].iter().any(|other| other.name == group.name) {
+                bail!(invalid(&format!("group '{}' is defined twice", group.name)));
+            }
+        }
+
+        Ok(Policy { threshold, groups })
+    }
+}
+
+/// Whether the name can be used in file names: only letters, digits, '-' and '_'.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parses a `NAME:K/N` group.
+fn parse_group(group: &str) -> Result<Group> {
+    let (name, k, n) = match (group.find(':'), group.find('/')) {
+        (Some(colon), Some(slash)) if colon < slash => (&group[..colon], &group[colon + 1..slash], &group[slash + 1..]),
+        _ => bail!("expected NAME:K/N"),
+    };
+
+    if !is_valid_name(name) {
+        bail!("names may only contain letters, digits, '-' and '_'");
+    }
+
+    let (k, n) = match (k.trim().parse::<u8>(), n.trim().parse::<u8>()) {
+        (Ok(k), Ok(n)) if (1..=n).contains(&k) => (k, n),
+        _ => bail!("expected 1 <= K <= N"),
+    };
+
+    Ok(Group {
+        name: name.to_string(),
+        k,
+        n,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let policy = "2 of (exec:2/3, eng:3/5)".parse::<Policy>().unwrap();
+
+        assert_eq!(policy.threshold, 2);
+        assert_eq!(
+            policy.group("eng"),
+            Some(&Group {
+                name: "eng".to_string(),
+                k: 3,
+                n: 5,
+            })
+        );
+        assert_eq!(policy.to_string(), "2 of (exec:2/3, eng:3/5)");
+        assert_eq!(policy.to_string().parse::<Policy>().unwrap(), policy);
+    }
+
+    #[test]
+    fn malformed_policies() {
+        for policy in &[
+            "",
+            "2 (exec:2/3, eng:3/5)",
+            "two of (exec:2/3, eng:3/5)",
+            "2 of exec:2/3, eng:3/5",
+            "2 of (exec:2/3, eng:3/5",
+            "0 of (exec:2/3, eng:3/5)",
+            "3 of (exec:2/3, eng:3/5)",
+            "1 of (exec:2/3, exec:3/5)",
+            "1 of (exec:4/3)",
+            "1 of (exec:0/3)",
+            "1 of (exec:2-3)",
+            "1 of (exec2/3)",
+            "1 of (:2/3)",
+            "1 of (../exec:2/3)",
+            "1 of (ex ec:2/3)",
+            "1 of (exec:2/300)",
+        ] {
+            assert!(policy.parse::<Policy>().is_err(), "'{}' should be rejected", policy);
+        }
+    }
+
+    #[test]
+    fn valid_names() {
+        assert!(is_valid_name("exec-team_2"));
+
+        for name in &["", ".", "..", "a/b", "a\\b", "a b", "é"] {
+            assert!(!is_valid_name(name), "'{}' should be rejected", name);
+        }
+    }
+}
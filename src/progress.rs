@@ -0,0 +1,67 @@
+//! Progress reporting through the logger, for operations on large secrets.
+
+/// How many bytes to process between two reports.
+const REPORT_INTERVAL: u64 = 64 * 1024 * 1024;
+
+pub struct Progress {
+    action: &'static str,
+    total: Option<u64>,
+    done: u64,
+    reported: u64,
+}
+
+impl Progress {
+    /// Starts tracking an operation, eg. "Encrypted", over `total` bytes if known.
+    pub fn new(action: &'static str, total: Option<u64>) -> Self {
+        Progress {
+            action,
+            total,
+            done: 0,
+            reported: 0,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: usize) {
+        self.done += bytes as u64;
+
+        if self.done - self.reported >= REPORT_INTERVAL {
+            self.reported = self.done;
+            self.report();
+        }
+    }
+
+    pub fn finish(&self) {
+        debug!("{} {}", self.action, format_size(self.done));
+    }
+
+    fn report(&self) {
+        match self.total {
+            Some(total) if total > 0 => debug!(
+                "{} {} of {} ({}%)",
+                self.action,
+                format_size(self.done),
+                format_size(total),
+                self.done * 100 / total
+            ),
+            _ => debug!("{} {}", self.action, format_size(self.done)),
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
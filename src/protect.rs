@@ -0,0 +1,237 @@
+//! Shares protected by a passphrase, for custodians who cannot manage key files.
+//!
+//! The share is encrypted with ChaCha20-Poly1305, under a key derived from the passphrase
+//! with Argon2id, whose parameters are recorded in the headers of the protected share:
+//!
+//! ```text
+//! -----BEGIN RUSTYSECRETS PROTECTED SHARE-----
+//! Kdf: argon2id
+//! Memory: 65536
+//! Iterations: 3
+//! Parallelism: 1
+//! Salt: 3q2+7wAAAAAAAAAAAAAAAA==
+//!
+//! NBqxn1ROFr2QgZfCdVGNe3QpnF8cJhAT2QeX0Ia1W1T3m6i5ppHWi3b4YXRsW6aQ
+//! ...
+//! -----END RUSTYSECRETS PROTECTED SHARE-----
+//! ```
+//!
+//! The data holds the nonce followed by the encrypted share and its tag. The headers are
+//! authenticated along with the share, so a tag which does not match means that either the
+//! passphrase is wrong or the share was tampered with, which is reported as a wrong passphrase.
+//!
+//! The headers are only authenticated once the key is derived, so shares asking for more than
+//! four times the memory, or more iterations or lanes than `MAX_ITERATIONS` and `MAX_PARALLELISM`,
+//! are rejected beforehand.
+
+use argon2::{self, Algorithm, Argon2, Version};
+use base64;
+use ring::aead::{self, OpeningKey, SealingKey, CHACHA20_POLY1305};
+use ring::rand::{SecureRandom, SystemRandom};
+use rpassword;
+
+use errors::*;
+
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const BEGIN: &str = "-----BEGIN RUSTYSECRETS PROTECTED SHARE-----";
+const END: &str = "-----END RUSTYSECRETS PROTECTED SHARE-----";
+const LINE_LEN: usize = 64;
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The memory used to derive the keys, in KiB.
+const MEMORY: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const PARALLELISM: u32 = 1;
+
+/// The costliest parameters accepted when reading a protected share.
+const MAX_MEMORY: u32 = 4 * MEMORY;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Where to get the passphrases of protected shares from.
+#[derive(Default)]
+pub enum Passphrases {
+    /// Ask for each passphrase on the terminal, without echoing it
+    #[default]
+    Prompt,
+    /// The same passphrase for every share
+    Single(String),
+    /// A passphrase per share, in the order the shares are protected or read
+    List(Vec<String>, Cell<usize>),
+}
+
+impl Passphrases {
+    /// The passphrase held by an environment variable.
+    pub fn from_env(var: &str) -> Result<Passphrases> {
+        let passphrase = env::var(var).chain_err(|| ErrorKind::CannotReadPassphrase(var.to_string()))?;
+
+        Ok(Passphrases::Single(passphrase))
+    }
+
+    /// The passphrases listed in a file, one per line.
+    pub fn from_file(path: &Path) -> Result<Passphrases> {
+        let content = fs::read_to_string(path)
+            .chain_err(|| ErrorKind::CannotReadPassphrases(format!("{}", path.display())))?;
+
+        Ok(Passphrases::List(content.lines().map(String::from).collect(), Cell::new(0)))
+    }
+
+    /// Returns the next passphrase, asking for it if needed, and for its confirmation when `confirm` is set.
+    pub fn next(&self, share: &str, confirm: bool) -> Result<String> {
+        match *self {
+            Passphrases::Prompt => {
+                let prompt = |what: &str| {
+                    rpassword::prompt_password(format!("{} for share {}: ", what, share))
+                        .chain_err(|| ErrorKind::CannotPromptPassphrase(share.to_string()))
+                };
+
+                let passphrase = prompt("Passphrase")?;
+                if confirm && prompt("Confirm passphrase")? != passphrase {
+                    bail!(ErrorKind::PassphraseMismatch(share.to_string()));
+                }
+
+                Ok(passphrase)
+            }
+            Passphrases::Single(ref passphrase) => Ok(passphrase.clone()),
+            Passphrases::List(ref passphrases, ref next) => {
+                let passphrase = passphrases
+                    .get(next.get())
+                    .ok_or_else(|| ErrorKind::MissingPassphrase(share.to_string()))?;
+                next.set(next.get() + 1);
+
+                Ok(passphrase.clone())
+            }
+        }
+    }
+}
+
+/// Whether the content is a protected share.
+pub fn is_protected(content: &[u8]) -> bool {
+    String::from_utf8_lossy(content).trim_start().starts_with(BEGIN)
+}
+
+/// Protects the share with the passphrase.
+pub fn protect(share: &[u8], passphrase: &str) -> Result<String> {
+    if passphrase.is_empty() {
+        bail!(ErrorKind::EmptyPassphrase);
+    }
+
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+
+    let rng = SystemRandom::new();
+    for bytes in &mut [&mut salt[..], &mut nonce[..]] {
+        rng.fill(bytes).map_err(|_| ErrorKind::CannotGenerateRandomBytes)?;
+    }
+
+    let headers = format!(
+        "{}\nKdf: argon2id\nMemory: {}\nIterations: {}\nParallelism: {}\nSalt: {}\n",
+        BEGIN,
+        MEMORY,
+        ITERATIONS,
+        PARALLELISM,
+        base64::encode(&salt)
+    );
+
+    let key = derive_key(passphrase, &salt, MEMORY, ITERATIONS, PARALLELISM)?;
+    let key = SealingKey::new(&CHACHA20_POLY1305, &key).map_err(|_| ErrorKind::CannotProtectShare)?;
+    let tag_len = CHACHA20_POLY1305.tag_len();
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(share);
+    data.resize(NONCE_LEN + share.len() + tag_len, 0);
+
+    aead::seal_in_place(&key, &nonce, headers.as_bytes(), &mut data[NONCE_LEN..], tag_len)
+        .map_err(|_| ErrorKind::CannotProtectShare)?;
+
+    let mut protected = headers;
+    protected.push('\n');
+
+    for line in base64::encode(&data).as_bytes().chunks(LINE_LEN) {
+        protected.push_str(&String::from_utf8_lossy(line));
+        protected.push('\n');
+    }
+    protected.push_str(END);
+    protected.push('\n');
+
+    Ok(protected)
+}
+
+/// Returns the share protected by the passphrase, failing with `WrongPassphrase` if the passphrase does not match.
+pub fn unprotect(content: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    // Shares may have gone through editors or systems which write CRLF line endings
+    let content = String::from_utf8_lossy(content)
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let content = content.trim();
+
+    if !content.starts_with(BEGIN) || !content.ends_with(END) {
+        bail!(ErrorKind::InvalidProtectedShare);
+    }
+
+    let (headers, body) = match content.find("\n\n") {
+        Some(blank) => (&content[..blank + 1], &content[blank + 2..content.len() - END.len()]),
+        None => bail!(ErrorKind::InvalidProtectedShare),
+    };
+
+    let header = |key: &str| {
+        headers
+            .lines()
+            .filter_map(|line| line.find(": ").map(|colon| (&line[..colon], &line[colon + 2..])))
+            .find(|&(k, _)| k == key)
+            .map(|(_, value)| value.trim())
+            .ok_or(ErrorKind::InvalidProtectedShare)
+    };
+    let number = |key: &str| {
+        header(key).and_then(|value| value.parse::<u32>().map_err(|_| ErrorKind::InvalidProtectedShare))
+    };
+
+    if header("Kdf")? != "argon2id" {
+        bail!(ErrorKind::InvalidProtectedShare);
+    }
+
+    let salt = base64::decode(header("Salt")?).map_err(|_| ErrorKind::InvalidProtectedShare)?;
+    let data = base64::decode(&body.lines().map(str::trim).collect::<String>())
+        .map_err(|_| ErrorKind::InvalidProtectedShare)?;
+
+    if data.len() < NONCE_LEN {
+        bail!(ErrorKind::InvalidProtectedShare);
+    }
+
+    let (memory, iterations, parallelism) = (number("Memory")?, number("Iterations")?, number("Parallelism")?);
+    if memory > MAX_MEMORY || iterations > MAX_ITERATIONS || parallelism > MAX_PARALLELISM {
+        bail!(ErrorKind::ProtectedShareTooCostly(memory, iterations, parallelism));
+    }
+
+    let key = derive_key(passphrase, &salt, memory, iterations, parallelism)?;
+    let key = OpeningKey::new(&CHACHA20_POLY1305, &key).map_err(|_| ErrorKind::InvalidProtectedShare)?;
+
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    let mut sealed = sealed.to_vec();
+
+    let share = aead::open_in_place(&key, nonce, headers.as_bytes(), 0, &mut sealed)
+        .map_err(|_| ErrorKind::WrongPassphrase)?;
+
+    Ok(share.to_vec())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], memory: u32, iterations: u32, parallelism: u32) -> Result<[u8; KEY_LEN]> {
+    let params = argon2::Params::new(memory, iterations, parallelism, Some(KEY_LEN))
+        .map_err(|_| ErrorKind::InvalidProtectedShare)?;
+
+    let mut key = [0; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| ErrorKind::InvalidProtectedShare)?;
+
+    Ok(key)
+}
@@ -0,0 +1,330 @@
+//! QR codes holding share files, so that shares can be printed on paper and scanned back.
+//!
+//! Codes are written as PNG or SVG images, with a caption under the code. PNG images are
+//! searched for a QR code like a scan would be, while SVG images are read back from the
+//! modules they draw, which only works with the images written by `split`.
+
+use font8x8::legacy::BASIC_LEGACY;
+use png;
+use qrcode::{Color, EcLevel, QrCode};
+use rqrr;
+
+use errors::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Size of a module of the code in PNG images, in pixels.
+const MODULE_SIZE: usize = 8;
+/// Width of the light border around the code, in modules.
+const QUIET_ZONE: usize = 4;
+/// Scale of the 8x8 glyphs of the caption.
+const GLYPH_SCALE: usize = 2;
+const LINE_HEIGHT: usize = 12 * GLYPH_SCALE;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The kind of image a QR code is written to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            ImageFormat::Png => "png",
+            ImageFormat::Svg => "svg",
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ImageFormat> {
+        match s {
+            "png" => Ok(ImageFormat::Png),
+            "svg" => Ok(ImageFormat::Svg),
+            _ => bail!("Unknown image format '{}'", s),
+        }
+    }
+}
+
+/// Encodes the content as a QR code, captioned with the given lines.
+pub fn render(content: &str, caption: &[String], format: ImageFormat) -> Result<Vec<u8>> {
+    let code = modules(content)?;
+    let layout = Layout::new(code.width, caption);
+
+    match format {
+        ImageFormat::Png => render_png(&code, caption, &layout),
+        ImageFormat::Svg => Ok(render_svg(&code, caption, &layout).into_bytes()),
+    }
+}
+
+/// The modules of a QR code, row by row.
+pub struct Modules {
+    /// Number of modules on each side of the code
+    pub width: usize,
+    dark: Vec<bool>,
+}
+
+impl Modules {
+    /// The coordinates of the dark modules, from the top left corner.
+    pub fn dark(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let width = self.width;
+        self.dark
+            .iter()
+            .enumerate()
+            .filter(|&(_, &dark)| dark)
+            .map(move |(i, _)| (i % width, i / width))
+    }
+}
+
+/// Encodes the content as a QR code.
+pub fn modules(content: &str) -> Result<Modules> {
+    let code = QrCode::with_error_correction_level(content.as_bytes(), EcLevel::M)
+        .chain_err(|| ErrorKind::CannotEncodeQrCode)?;
+
+    Ok(Modules {
+        width: code.width(),
+        dark: code.to_colors().into_iter().map(|color| color == Color::Dark).collect(),
+    })
+}
+
+/// Whether the given file content is an image rather than a share file.
+pub fn is_image(bytes: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&bytes[..bytes.len().min(64)]);
+    let start = start.trim_start();
+
+    bytes.starts_with(PNG_SIGNATURE) || start.starts_with("<?xml") || start.starts_with("<svg")
+}
+
+/// Decodes the QR code found in the image.
+pub fn decode(bytes: &[u8]) -> Result<String> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        decode_png(bytes)
+    } else {
+        decode_svg(&String::from_utf8_lossy(bytes))
+    }
+}
+
+/// Where the code and its caption go, in pixels.
+struct Layout {
+    width: usize,
+    height: usize,
+    code_x: usize,
+    code_size: usize,
+}
+
+impl Layout {
+    fn new(modules: usize, caption: &[String]) -> Layout {
+        let code_size = (modules + 2 * QUIET_ZONE) * MODULE_SIZE;
+        let caption_width = caption.iter().map(|line| line.chars().count()).max().unwrap_or(0) * 8 * GLYPH_SCALE;
+        let width = code_size.max(caption_width + 2 * QUIET_ZONE * MODULE_SIZE);
+
+        Layout {
+            width,
+            height: code_size + caption.len() * LINE_HEIGHT + QUIET_ZONE * MODULE_SIZE,
+            code_x: (width - code_size) / 2,
+            code_size,
+        }
+    }
+
+    /// The top of the given caption line.
+    fn line_y(&self, line: usize) -> usize {
+        self.code_size + line * LINE_HEIGHT
+    }
+}
+
+fn render_png(code: &Modules, caption: &[String], layout: &Layout) -> Result<Vec<u8>> {
+    let mut pixels = vec![0xFF; layout.width * layout.height];
+
+    for (x, y) in code.dark() {
+        let x = layout.code_x + (QUIET_ZONE + x) * MODULE_SIZE;
+        let y = (QUIET_ZONE + y) * MODULE_SIZE;
+        for row in y..y + MODULE_SIZE {
+            for pixel in &mut pixels[row * layout.width + x..row * layout.width + x + MODULE_SIZE] {
+                *pixel = 0;
+            }
+        }
+    }
+
+    for (line, text) in caption.iter().enumerate() {
+        let text_width = text.chars().count() * 8 * GLYPH_SCALE;
+        let x = (layout.width - text_width) / 2;
+        draw_text(&mut pixels, layout.width, x, layout.line_y(line), text);
+    }
+
+    let mut image = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut image, layout.width as u32, layout.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().chain_err(|| ErrorKind::CannotEncodeQrCode)?;
+        writer
+            .write_image_data(&pixels)
+            .chain_err(|| ErrorKind::CannotEncodeQrCode)?;
+    }
+
+    Ok(image)
+}
+
+/// Draws the text with the 8x8 font, replacing the characters it lacks with '?'.
+fn draw_text(pixels: &mut [u8], width: usize, x: usize, y: usize, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        let glyph = BASIC_LEGACY[if c.is_ascii() { c as usize } else { '?' as usize }];
+
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in (0..8).filter(|column| bits & (1 << column) != 0) {
+                let left = x + (i * 8 + column) * GLYPH_SCALE;
+                let top = y + row * GLYPH_SCALE;
+
+                for py in top..top + GLYPH_SCALE {
+                    for pixel in &mut pixels[py * width + left..py * width + left + GLYPH_SCALE] {
+                        *pixel = 0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn render_svg(code: &Modules, caption: &[String], layout: &Layout) -> String {
+    let path = code
+        .dark()
+        .map(|(x, y)| format!("M{},{}h1v1h-1z", x, y))
+        .collect::<String>();
+
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#fff\"/>\n\
+         <path transform=\"translate({x},{y}) scale({s})\" fill=\"#000\" d=\"{d}\"/>\n",
+        w = layout.width,
+        h = layout.height,
+        x = layout.code_x + QUIET_ZONE * MODULE_SIZE,
+        y = QUIET_ZONE * MODULE_SIZE,
+        s = MODULE_SIZE,
+        d = path
+    );
+
+    for (line, text) in caption.iter().enumerate() {
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" text-anchor=\"middle\">{}</text>\n",
+            layout.width / 2,
+            layout.line_y(line) + 8 * GLYPH_SCALE,
+            8 * GLYPH_SCALE,
+            escape_xml(text)
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn decode_png(bytes: &[u8]) -> Result<String> {
+    // Images are read with 8 bits per sample, whether they hold palettes, fewer bits or 16 bits
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info().chain_err(|| ErrorKind::CannotDecodeImage)?;
+
+    if info.bit_depth != png::BitDepth::Eight {
+        bail!(ErrorKind::CannotDecodeImage);
+    }
+
+    let mut data = vec![0; info.buffer_size()];
+    reader.next_frame(&mut data).chain_err(|| ErrorKind::CannotDecodeImage)?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::RGB => 3,
+        png::ColorType::RGBA => 4,
+        png::ColorType::Indexed => bail!(ErrorKind::CannotDecodeImage),
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut image = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| {
+        let pixel = &data[y * info.line_size + x * channels..][..channels];
+        luminance(pixel)
+    });
+
+    let mut error = None;
+    for grid in image.detect_grids() {
+        match grid.decode() {
+            Ok((_, content)) => return Ok(content),
+            Err(e) => error = Some(e),
+        }
+    }
+
+    match error {
+        Some(e) => Err(e).chain_err(|| ErrorKind::InvalidQrCode),
+        None => bail!(ErrorKind::QrCodeNotFound),
+    }
+}
+
+/// The luminance of a pixel over a white background.
+fn luminance(pixel: &[u8]) -> u8 {
+    let (value, alpha) = match *pixel {
+        [gray] => (u32::from(gray), 255),
+        [gray, alpha] => (u32::from(gray), u32::from(alpha)),
+        [r, g, b] => ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000, 255),
+        [r, g, b, alpha] => (
+            (299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000,
+            u32::from(alpha),
+        ),
+        _ => (255, 255),
+    };
+
+    ((value * alpha + 255 * (255 - alpha)) / 255) as u8
+}
+
+/// Reads the modules back from the path drawn by `render_svg`.
+fn decode_svg(svg: &str) -> Result<String> {
+    let path = match svg.find(" d=\"M") {
+        Some(start) => &svg[start + 4..],
+        None => bail!(ErrorKind::QrCodeNotFound),
+    };
+    let path = &path[..path.find('"').unwrap_or(path.len())];
+
+    let mut dark = Vec::new();
+    for module in path.split('M').skip(1) {
+        let mut coords = module
+            .split(&[',', 'h'][..])
+            .take(2)
+            .map(|coord| coord.parse::<usize>());
+
+        match (coords.next(), coords.next()) {
+            (Some(Ok(x)), Some(Ok(y))) => dark.push((x, y)),
+            _ => bail!(ErrorKind::QrCodeNotFound),
+        }
+    }
+
+    // The finder pattern at the top right corner spans the last column
+    let size = dark.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+    let mut modules = vec![false; size * size];
+    for &(x, y) in &dark {
+        if y >= size {
+            bail!(ErrorKind::QrCodeNotFound);
+        }
+        modules[y * size + x] = true;
+    }
+
+    let grid = rqrr::SimpleGrid::from_func(size, |x, y| modules[y * size + x]);
+    let (_, content) = rqrr::Grid::new(grid).decode().chain_err(|| ErrorKind::InvalidQrCode)?;
+
+    Ok(content)
+}
@@ -0,0 +1,256 @@
+//! Finding the shares which are consistent with each other among a set of shares
+//! containing corrupted ones, or ones belonging to a different split.
+
+use ring::rand::{SecureRandom, SystemRandom};
+use rusty_secrets::sss;
+
+use errors::*;
+use share::Share;
+
+use std::collections::HashMap;
+
+/// The most recoveries to try before giving up, each subset of k shares taking up to one per share.
+const MAX_RECOVERIES: u64 = 5000;
+
+/// The largest set of shares which lie on the same polynomial.
+pub struct Quorum {
+    /// Indexes of the shares consistent with each other
+    pub good: Vec<usize>,
+    /// Indexes of the other shares
+    pub bad: Vec<usize>,
+    /// The secret recovered from the good shares, before unwrapping
+    pub secret: Vec<u8>,
+}
+
+/// Tries every subset of k shares, and looks for the one agreeing with the most other shares.
+///
+/// Without signatures, k points always define a polynomial, so telling a single bad share apart
+/// requires at least k + 2 shares. When `verify_signatures` is set, any subset with valid
+/// signatures is trusted, and unsigned shares are considered bad.
+///
+/// When there are too many subsets to try them all, random subsets are tried instead, until
+/// one of them agrees with so many shares that no other subset could beat it.
+pub fn find_quorum(shares: &[Share], verify_signatures: bool) -> Result<Quorum> {
+    let threshold = match most_common_threshold(shares) {
+        Some(threshold) => threshold,
+        None => bail!(ErrorKind::NoConsistentQuorum),
+    };
+
+    let candidates = (0..shares.len())
+        .filter(|&i| shares[i].threshold == threshold && (shares[i].signed || !verify_signatures))
+        .collect::<Vec<_>>();
+
+    let (k, m) = (threshold as usize, candidates.len());
+
+    if m < k {
+        bail!(ErrorKind::NoConsistentQuorum);
+    }
+
+    // Two distinct polynomials share at most k - 1 points, so a subset agreeing
+    // with more than half of m + k - 1 shares cannot be beaten by another one.
+    let is_unbeatable = |agreeing: usize| 2 * agreeing > m + k - 1;
+
+    let exhaustive = combinations(m, k).saturating_mul((m - k + 1) as u64) <= MAX_RECOVERIES;
+    let subsets: Box<dyn Iterator<Item = Vec<usize>>> = if exhaustive {
+        Box::new(Combinations::new(m, k))
+    } else {
+        debug!("Too many subsets of {} out of {} shares, trying random ones", k, m);
+        Box::new(RandomSubsets::new(m, k))
+    };
+
+    let mut best: Option<(Vec<usize>, Vec<u8>)> = None;
+    let mut tied = false;
+    let mut proven = false;
+    let (mut tried, mut recoveries) = (0, 0);
+
+    for subset in subsets {
+        if recoveries >= MAX_RECOVERIES {
+            break;
+        }
+        tried += 1;
+        recoveries += 1;
+
+        let quorum = subset.iter().map(|&i| &shares[candidates[i]]).collect::<Vec<_>>();
+
+        let secret = match recover(&quorum, verify_signatures) {
+            Some(secret) => secret,
+            None => continue,
+        };
+
+        let mut agreeing = subset.iter().map(|&i| candidates[i]).collect::<Vec<_>>();
+        let mut left = m - k;
+
+        for (i, &candidate) in candidates.iter().enumerate() {
+            if subset.contains(&i) {
+                continue;
+            }
+
+            // Random subsets are only of use if they cannot be beaten
+            if !exhaustive && !is_unbeatable(agreeing.len() + left) {
+                break;
+            }
+            left -= 1;
+            recoveries += 1;
+
+            // Swapping a member of the quorum for another share only
+            // recovers the same secret if that share lies on the same polynomial.
+            let mut swapped = quorum[1..].to_vec();
+            swapped.push(&shares[candidate]);
+
+            if recover(&swapped, verify_signatures).as_ref() == Some(&secret) {
+                agreeing.push(candidate);
+            }
+        }
+
+        if let Some((ref good, ref best_secret)) = best {
+            if good.len() > agreeing.len() {
+                continue;
+            }
+            if good.len() == agreeing.len() {
+                tied = tied || *best_secret != secret;
+                continue;
+            }
+        }
+
+        let unbeatable = is_unbeatable(agreeing.len());
+
+        tied = false;
+        best = Some((agreeing, secret));
+
+        if unbeatable {
+            proven = true;
+            break;
+        }
+    }
+
+    // The best of the random subsets may not be the best of all
+    if !exhaustive && !proven {
+        bail!(ErrorKind::QuorumSearchGaveUp(tried, threshold, m));
+    }
+
+    let (mut good, secret) = match best {
+        Some(best) => best,
+        None => bail!(ErrorKind::NoConsistentQuorum),
+    };
+
+    if tied || (!verify_signatures && good.len() == k && m > k) {
+        bail!(ErrorKind::AmbiguousQuorum(threshold));
+    }
+
+    good.sort();
+
+    let bad = (0..shares.len()).filter(|i| !good.contains(i)).collect();
+
+    Ok(Quorum { good, bad, secret })
+}
+
+fn most_common_threshold(shares: &[Share]) -> Option<u8> {
+    let mut counts = HashMap::new();
+
+    for share in shares {
+        *counts.entry(share.threshold).or_insert(0) += 1;
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|&(threshold, count)| (count, threshold))
+        .map(|(threshold, _)| threshold)
+}
+
+fn recover(shares: &[&Share], verify_signatures: bool) -> Option<Vec<u8>> {
+    let shares = shares
+        .iter()
+        .map(|share| share.as_str().to_string())
+        .collect::<Vec<_>>();
+
+    sss::recover_secret(&shares, verify_signatures).ok()
+}
+
+/// The number of k-sized subsets of `0..n`, saturating at `u64::MAX`.
+fn combinations(n: usize, k: usize) -> u64 {
+    (0..k.min(n - k) as u64).fold(1u64, |count, i| {
+        // Exact, since the product of i + 1 consecutive numbers is divisible by (i + 1)!
+        count
+            .checked_mul(n as u64 - i)
+            .map_or(u64::MAX, |count| count / (i + 1))
+    })
+}
+
+/// Iterates over random k-sized subsets of `0..n`, in order, without end.
+struct RandomSubsets {
+    n: usize,
+    k: usize,
+    rng: SystemRandom,
+}
+
+impl RandomSubsets {
+    fn new(n: usize, k: usize) -> Self {
+        RandomSubsets {
+            n,
+            k,
+            rng: SystemRandom::new(),
+        }
+    }
+}
+
+impl Iterator for RandomSubsets {
+    type Item = Vec<usize>;
+
+    /// Picks the first k indexes of a partial Fisher-Yates shuffle.
+    fn next(&mut self) -> Option<Vec<usize>> {
+        let mut indexes = (0..self.n).collect::<Vec<_>>();
+
+        for i in 0..self.k {
+            let mut bytes = [0; 4];
+            self.rng.fill(&mut bytes).ok()?;
+
+            let random = bytes.iter().fold(0usize, |random, &byte| (random << 8) | usize::from(byte));
+            indexes.swap(i, i + random % (self.n - i));
+        }
+
+        let mut subset = indexes[..self.k].to_vec();
+        subset.sort();
+
+        Some(subset)
+    }
+}
+
+/// Iterates over the k-sized subsets of `0..n`, in lexicographic order.
+struct Combinations {
+    n: usize,
+    indexes: Option<Vec<usize>>,
+}
+
+impl Combinations {
+    fn new(n: usize, k: usize) -> Self {
+        let indexes = if k <= n { Some((0..k).collect()) } else { None };
+
+        Combinations { n, indexes }
+    }
+}
+
+impl Iterator for Combinations {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        let current = self.indexes.take()?;
+        let k = current.len();
+
+        let mut next = current.clone();
+        let mut i = k;
+
+        while i > 0 && next[i - 1] == self.n - k + i - 1 {
+            i -= 1;
+        }
+
+        if i > 0 {
+            next[i - 1] += 1;
+            for j in i..k {
+                next[j] = next[j - 1] + 1;
+            }
+            self.indexes = Some(next);
+        }
+
+        Some(current)
+    }
+}
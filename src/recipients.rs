@@ -0,0 +1,69 @@
+//! Recipients of encrypted shares, given as `N=PUBKEY`, where `N` is the number of the share,
+//! from 1 as in its `Index` header, and `PUBKEY` the age public key of its custodian, such as `age1...`.
+//!
+//! Recipients files list a recipient per line, and may hold blank lines and `#` comments.
+
+use age::Recipient;
+use errors::*;
+
+use std::fs;
+use std::path::Path;
+
+/// Parses a `N=PUBKEY` recipient.
+pub fn parse(value: &str) -> Result<(u8, Recipient)> {
+    let (num, key) = match value.find('=') {
+        Some(eq) => (value[..eq].trim(), value[eq + 1..].trim()),
+        None => bail!(ErrorKind::InvalidRecipients(format!("expected N=PUBKEY, got '{}'", value))),
+    };
+
+    let num = num
+        .parse::<u8>()
+        .chain_err(|| ErrorKind::InvalidRecipients(format!("invalid share number '{}'", num)))?;
+
+    Ok((num, key.parse()?))
+}
+
+/// Reads the recipients listed in the file.
+pub fn load(path: &Path) -> Result<Vec<(u8, Recipient)>> {
+    let content =
+        fs::read_to_string(path).chain_err(|| ErrorKind::CannotReadRecipients(format!("{}", path.display())))?;
+
+    content
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            parse(line).chain_err(|| {
+                ErrorKind::InvalidRecipients(format!("invalid line {} of '{}'", i + 1, path.display()))
+            })
+        })
+        .collect()
+}
+
+/// Makes sure that each of the `n` shares is given exactly one recipient.
+pub fn check(recipients: &[(u8, Recipient)], n: u8) -> Result<()> {
+    for (i, &(num, _)) in recipients.iter().enumerate() {
+        if num == 0 || num > n {
+            bail!(ErrorKind::InvalidRecipients(format!("there is no share #{} out of {} shares", num, n)));
+        }
+
+        if recipients[..i].iter().any(|&(other, _)| other == num) {
+            bail!(ErrorKind::InvalidRecipients(format!("share #{} is given twice", num)));
+        }
+    }
+
+    let missing = (1..=n)
+        .filter(|num| !recipients.iter().any(|(other, _)| other == num))
+        .map(|num| format!("#{}", num))
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        bail!(ErrorKind::InvalidRecipients(format!(
+            "no recipient for shares {}, every share must be encrypted",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,446 @@
+//! Share files.
+//!
+//! A share file holds a single share as produced by RustySecrets, formatted as `K-N-D`,
+//! optionally preceded by `Key: Value` header lines carrying metadata about the share:
+//!
+//! ```text
+//! Mode: wrapped
+//!
+//! 7-1-ChaydsUJDypD9ZWxwvIICh/cmZvzusOF
+//! ```
+//!
+//! Shares never contain a colon, which lets us tell the headers and the share apart.
+//! The share may be split into groups of characters separated by spaces, as printed on share cards.
+//! The share may also be written as a sequence of words, over several lines:
+//!
+//! ```text
+//! Mode: wrapped
+//!
+//! ability drill dial curious bridge copper
+//! ...
+//! ```
+//!
+//! SLIP-39 mnemonics are told apart from the others by their word list.
+//!
+//! Shares may also be armored, see the `armor` module, in which case only the armored
+//! blocks are read and any text around them is ignored.
+//!
+//! Several shares may follow each other, separated by blank lines.
+
+use armor;
+use base64;
+use protobuf::{self, CodedInputStream, Message};
+use rusty_secrets::proto::wrapped::ShareProto;
+
+use digest;
+use errors::*;
+use mnemonic;
+use slip39;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Whether a share was generated from a raw secret or from a secret wrapped along its MIME type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Raw,
+    Wrapped,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mode::Raw => write!(f, "raw"),
+            Mode::Wrapped => write!(f, "wrapped"),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Mode> {
+        match s {
+            "raw" => Ok(Mode::Raw),
+            "wrapped" => Ok(Mode::Wrapped),
+            _ => bail!(ErrorKind::InvalidShare(format!("unknown mode '{}'", s))),
+        }
+    }
+}
+
+/// How a share is written out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The share as produced by RustySecrets
+    Text,
+    /// A sequence of words, see the `mnemonic` module
+    Mnemonic,
+    /// A SLIP-39 mnemonic, which holds a share of a different scheme than RustySecrets
+    Slip39,
+    /// An ASCII armored block, see the `armor` module
+    Armor,
+}
+
+impl Format {
+    /// The usual extension of share files in this format.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Format::Armor => "asc",
+            Format::Text | Format::Mnemonic | Format::Slip39 => "txt",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Format::Text => write!(f, "text"),
+            Format::Mnemonic => write!(f, "mnemonic"),
+            Format::Slip39 => write!(f, "slip39"),
+            Format::Armor => write!(f, "armor"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "text" => Ok(Format::Text),
+            "mnemonic" => Ok(Format::Mnemonic),
+            "slip39" => Ok(Format::Slip39),
+            "armor" => Ok(Format::Armor),
+            _ => bail!("Unknown share format '{}'", s),
+        }
+    }
+}
+
+/// Number of words per line of mnemonic shares.
+const WORDS_PER_LINE: usize = 6;
+
+#[derive(Clone, Debug)]
+pub struct Share {
+    /// The identifier of the share, between 1 and n
+    pub id: u8,
+    /// The number of shares needed to recover the secret
+    pub threshold: u8,
+    /// The Shamir data held by the share
+    pub data: Vec<u8>,
+    /// Whether the share carries a signature
+    pub signed: bool,
+    /// Where the share was read from, if anywhere
+    pub source: Option<String>,
+    /// How the share is written out
+    pub format: Format,
+    headers: Vec<(String, String)>,
+    share: String,
+}
+
+impl Share {
+    /// Builds a share from a string produced by RustySecrets.
+    pub fn new(share: String, mode: Mode) -> Result<Share> {
+        let mut share = Self::parse(&share)?;
+        share.set_header("Mode", &mode.to_string());
+        Ok(share)
+    }
+
+    /// Builds an unsigned share out of its Shamir data.
+    pub fn from_data(threshold: u8, id: u8, data: Vec<u8>, mode: Mode) -> Result<Share> {
+        let mut proto = ShareProto::new();
+        proto.set_shamir_data(data);
+
+        let bytes = proto
+            .write_to_bytes()
+            .chain_err(|| ErrorKind::InvalidShare("cannot encode share data".to_string()))?;
+        let share = format!(
+            "{}-{}-{}",
+            threshold,
+            id,
+            base64::encode_config(&bytes, base64::STANDARD_NO_PAD)
+        );
+
+        Self::new(share, mode)
+    }
+
+    /// Wraps a SLIP-39 share, identified by its member index within its group.
+    pub fn from_slip39(share: &slip39::Share) -> Share {
+        Share {
+            id: share.member_index + 1,
+            threshold: share.member_threshold,
+            data: share.value.clone(),
+            signed: false,
+            source: None,
+            format: Format::Slip39,
+            headers: Vec::new(),
+            share: share.to_words().join(" "),
+        }
+    }
+
+    /// Parses the content of a share file.
+    pub fn parse(content: &str) -> Result<Share> {
+        let mut shares = Self::parse_all(content)?;
+
+        match shares.len() {
+            0 => bail!(ErrorKind::InvalidShare("no share found".to_string())),
+            1 => Ok(shares.remove(0)),
+            _ => bail!(ErrorKind::InvalidShare("found more than one share".to_string())),
+        }
+    }
+
+    /// Parses any number of shares, each one preceded by its headers.
+    pub fn parse_all(content: &str) -> Result<Vec<Share>> {
+        if armor::is_armored(content) {
+            return armor::decode_all(content)?
+                .into_iter()
+                .map(Self::from_armor)
+                .collect();
+        }
+
+        let mut shares = Vec::new();
+        let mut headers = Vec::new();
+        let mut body = Vec::new();
+
+        for line in content.lines().map(str::trim) {
+            let is_header = line.contains(':');
+            // Only the first line of a text share holds minus signs, so text shares may follow each other
+            let is_text_share = !is_header && line.contains('-');
+
+            // A blank line, a header or another text share ends the share being read, if any
+            if (line.is_empty() || is_header || is_text_share) && !body.is_empty() {
+                shares.push(Self::parse_body(&body, &headers)?);
+                headers.clear();
+                body.clear();
+            }
+
+            if is_header {
+                let (key, value) = line.split_at(line.find(':').unwrap());
+                headers.push((key.trim().to_string(), value[1..].trim().to_string()));
+            } else if !line.is_empty() {
+                body.push(line);
+            }
+        }
+
+        if !body.is_empty() {
+            shares.push(Self::parse_body(&body, &headers)?);
+        } else if !headers.is_empty() {
+            bail!(ErrorKind::InvalidShare("no share found after the headers".to_string()));
+        }
+
+        Ok(shares)
+    }
+
+    fn parse_body(body: &[&str], headers: &[(String, String)]) -> Result<Share> {
+        let words = body
+            .iter()
+            .flat_map(|line| line.split_whitespace())
+            .collect::<Vec<_>>();
+
+        // Text shares may have been typed back in groups of characters, words never contain a minus sign
+        if words.len() == 1 || words[0].contains('-') {
+            return Self::parse_share(words.concat(), headers.to_vec(), Format::Text);
+        }
+
+        // Both word lists have words in common, but their mnemonics are mostly made of their own words
+        let slip39_words = words.iter().filter(|word| slip39::is_word(word)).count();
+        let bip39_words = words.iter().filter(|word| mnemonic::is_word(word)).count();
+
+        if slip39_words > bip39_words {
+            let mut share = Self::from_slip39(&slip39::Share::from_words(&words)?);
+            share.headers = headers.to_vec();
+            return Ok(share);
+        }
+
+        let bytes = mnemonic::decode(&words)?;
+
+        if bytes.len() < 2 {
+            bail!(ErrorKind::InvalidShare("mnemonic is too short".to_string()));
+        }
+
+        let share = format!(
+            "{}-{}-{}",
+            bytes[0],
+            bytes[1],
+            base64::encode_config(&bytes[2..], base64::STANDARD_NO_PAD)
+        );
+
+        Self::parse_share(share, headers.to_vec(), Format::Mnemonic)
+    }
+
+    fn from_armor(block: armor::Block) -> Result<Share> {
+        let share = format!(
+            "{}-{}-{}",
+            block.threshold,
+            block.id,
+            base64::encode_config(&block.data, base64::STANDARD_NO_PAD)
+        );
+
+        Self::parse_share(share, block.headers, Format::Armor)
+    }
+
+    fn parse_share(share: String, headers: Vec<(String, String)>, format: Format) -> Result<Share> {
+        let parts = share.split('-').collect::<Vec<_>>();
+        if parts.len() != 3 {
+            bail!(ErrorKind::InvalidShare(
+                "expected 3 parts separated by a minus sign".to_string()
+            ));
+        }
+
+        let threshold = parts[0]
+            .parse::<u8>()
+            .chain_err(|| ErrorKind::InvalidShare(format!("invalid threshold '{}'", parts[0])))?;
+        let id = parts[1]
+            .parse::<u8>()
+            .chain_err(|| ErrorKind::InvalidShare(format!("invalid share identifier '{}'", parts[1])))?;
+
+        // Share identifiers start from 1, and a share #0 would hold the secret itself
+        if id == 0 {
+            bail!(ErrorKind::InvalidShare(format!("invalid share identifier '{}'", parts[1])));
+        }
+
+        let bytes = base64::decode_config(parts[2], base64::STANDARD_NO_PAD)
+            .chain_err(|| ErrorKind::InvalidShare("cannot decode share data".to_string()))?;
+        let proto = protobuf::parse_from_bytes::<ShareProto>(&bytes)
+            .chain_err(|| ErrorKind::InvalidShare("cannot decode share data".to_string()))?;
+
+        let share = Share {
+            id,
+            threshold,
+            data: proto.get_shamir_data().to_vec(),
+            signed: !proto.get_signature().is_empty(),
+            source: None,
+            format,
+            headers,
+            share,
+        };
+
+        // Reject unknown modes early
+        share.mode()?;
+
+        Ok(share)
+    }
+
+    /// The share as expected by RustySecrets, or the SLIP-39 mnemonic, without any header.
+    pub fn as_str(&self) -> &str {
+        &self.share
+    }
+
+    /// Where the share was read from, for use in messages.
+    pub fn source(&self) -> &str {
+        self.source.as_ref().map_or("<unknown>", String::as_str)
+    }
+
+    /// The mode of the share, if known.
+    pub fn mode(&self) -> Result<Option<Mode>> {
+        self.header("Mode")
+            .map(str::parse)
+            .map_or(Ok(None), |mode| mode.map(Some))
+    }
+
+    /// Identifies the set of shares the share belongs to, if known.
+    pub fn set_id(&self) -> Option<String> {
+        match self.format {
+            Format::Slip39 => slip39::Share::from_words(&self.share.split(' ').collect::<Vec<_>>())
+                .ok()
+                .map(|share| format!("{:04x}", share.identifier)),
+            Format::Text | Format::Mnemonic | Format::Armor => self.header("Set-Id").map(String::from),
+        }
+    }
+
+    /// The hex-encoded root of the Merkle tree the share was signed with, if signed.
+    pub fn signature_root(&self) -> Option<String> {
+        if !self.signed {
+            return None;
+        }
+
+        let proto = protobuf::parse_from_bytes::<ShareProto>(&self.proto_bytes()).ok()?;
+
+        root_hash(proto.get_proof()).map(|root| digest::to_hex(&root))
+    }
+
+    /// The share encoded as words, holding its threshold, identifier and data.
+    fn words(&self) -> Vec<&'static str> {
+        let mut bytes = vec![self.threshold, self.id];
+        bytes.extend(self.proto_bytes());
+
+        mnemonic::encode(&bytes)
+    }
+
+    /// The encoded protobuf message holding the Shamir data and signature of the share.
+    fn proto_bytes(&self) -> Vec<u8> {
+        let data = self.share.splitn(3, '-').nth(2).expect("share was validated when parsed");
+
+        base64::decode_config(data, base64::STANDARD_NO_PAD).expect("share was validated when parsed")
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+        self.headers.push((key.to_string(), value.to_string()));
+    }
+}
+
+/// Reads the root hash out of an encoded Merkle proof, where it is the first field.
+fn root_hash(proof: &[u8]) -> Option<Vec<u8>> {
+    let mut input = CodedInputStream::from_bytes(proof);
+
+    while !input.eof().ok()? {
+        let (field, wire_type) = input.read_tag_unpack().ok()?;
+
+        if field == 1 {
+            return input.read_bytes().ok();
+        }
+
+        input.skip_field(wire_type).ok()?;
+    }
+
+    None
+}
+
+impl fmt::Display for Share {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines = match self.format {
+            Format::Text => vec![self.share.clone()],
+            Format::Mnemonic => self
+                .words()
+                .chunks(WORDS_PER_LINE)
+                .map(|line| line.join(" "))
+                .collect(),
+            Format::Slip39 => self
+                .share
+                .split(' ')
+                .collect::<Vec<_>>()
+                .chunks(WORDS_PER_LINE)
+                .map(|line| line.join(" "))
+                .collect(),
+            // The armor carries the headers, along with the threshold and identifier
+            Format::Armor => {
+                return write!(
+                    f,
+                    "{}",
+                    armor::encode(self.id, self.threshold, &self.headers, &self.proto_bytes())
+                );
+            }
+        };
+
+        for (key, value) in &self.headers {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+
+        if !self.headers.is_empty() {
+            writeln!(f)?;
+        }
+
+        for line in lines {
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Encryption of the master secret with a passphrase, using a 4-round Feistel network
+//! whose round function is PBKDF2-HMAC-SHA256.
+
+use ring::digest::SHA256;
+use ring::pbkdf2;
+
+const ROUNDS: u8 = 4;
+const BASE_ITERATIONS: u32 = 10_000;
+const SALT_PREFIX: &[u8] = b"shamir";
+
+pub fn encrypt(
+    master_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    feistel(master_secret, passphrase, iteration_exponent, identifier, extendable, 0..ROUNDS)
+}
+
+pub fn decrypt(
+    encrypted_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Vec<u8> {
+    feistel(encrypted_secret, passphrase, iteration_exponent, identifier, extendable, (0..ROUNDS).rev())
+}
+
+fn feistel<I: Iterator<Item = u8>>(
+    input: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    rounds: I,
+) -> Vec<u8> {
+    let (left, right) = input.split_at(input.len() / 2);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+
+    // Extendable share sets keep the same encryption whatever their identifier
+    let mut salt = Vec::new();
+    if !extendable {
+        salt.extend_from_slice(SALT_PREFIX);
+        salt.push((identifier >> 8) as u8);
+        salt.push(identifier as u8);
+    }
+
+    let iterations = (BASE_ITERATIONS << iteration_exponent) / u32::from(ROUNDS);
+
+    for round in rounds {
+        let mut password = vec![round];
+        password.extend_from_slice(passphrase);
+
+        let mut round_salt = salt.clone();
+        round_salt.extend_from_slice(&right);
+
+        let mut key = vec![0; right.len()];
+        pbkdf2::derive(&SHA256, iterations, &round_salt, &password, &mut key);
+
+        for (byte, k) in left.iter_mut().zip(key) {
+            *byte ^= k;
+        }
+
+        ::std::mem::swap(&mut left, &mut right);
+    }
+
+    right.extend_from_slice(&left);
+    right
+}
@@ -0,0 +1,577 @@
+//! SLIP-0039 shares, as used by hardware wallets.
+//!
+//! The master secret is encrypted with a passphrase, then split in two levels: into groups,
+//! each of which is split again into member shares. Recovering the secret takes the member
+//! threshold of shares in each of the group threshold of groups.
+//! See <https://github.com/satoshilabs/slips/blob/master/slip-0039.md>.
+
+mod cipher;
+mod rs1024;
+
+use ring::digest::SHA256;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use errors::*;
+use gf256::Gf256;
+
+use std::collections::BTreeMap;
+
+const WORD_LIST: &str = include_str!("english.txt");
+const BITS_PER_WORD: usize = 10;
+const PREFIX_LEN: usize = 4;
+
+/// Words taken by the identifier, extendable flag, iteration exponent, and group and member parameters.
+const HEADER_WORDS: usize = 4;
+const MIN_SECRET_LEN: usize = 16;
+/// The 128 bits of the shortest secrets take 13 words.
+const MIN_WORDS: usize = HEADER_WORDS + 13 + rs1024::CHECKSUM_WORDS;
+const MAX_SHARE_COUNT: u8 = 16;
+
+const CUSTOMIZATION: &[u8] = b"shamir";
+const CUSTOMIZATION_EXTENDABLE: &[u8] = b"shamir_extendable";
+
+const DIGEST_LEN: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+
+fn word_list() -> Vec<&'static str> {
+    WORD_LIST.lines().collect()
+}
+
+/// Whether the given word is in the SLIP-39 word list.
+pub fn is_word(word: &str) -> bool {
+    find_word(&word_list(), word).is_some()
+}
+
+/// Looks a word up, either in full or by its first letters.
+fn find_word(list: &[&str], word: &str) -> Option<usize> {
+    let word = word.to_lowercase();
+
+    if let Ok(index) = list.binary_search(&word.as_str()) {
+        return Some(index);
+    }
+
+    if word.len() < PREFIX_LEN {
+        return None;
+    }
+
+    list.iter().position(|candidate| candidate.starts_with(&word))
+}
+
+/// How to split a master secret.
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// Number of groups needed to recover the secret
+    pub group_threshold: u8,
+    /// Member threshold and member count of each group
+    pub groups: Vec<(u8, u8)>,
+    /// Makes the passphrase encryption 2^e times slower
+    pub iteration_exponent: u8,
+    pub passphrase: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Share {
+    /// Random identifier common to all the shares of a master secret
+    pub identifier: u16,
+    /// Whether the encryption of the master secret is independent of the identifier
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: Vec<u8>,
+}
+
+impl Share {
+    /// Decodes a mnemonic, checking its checksum.
+    pub fn from_words(words: &[&str]) -> Result<Share> {
+        let list = word_list();
+
+        let mut indexes = Vec::with_capacity(words.len());
+
+        for (position, word) in words.iter().enumerate() {
+            match find_word(&list, word) {
+                Some(index) => indexes.push(index as u16),
+                None => bail!(ErrorKind::UnknownMnemonicWord(position + 1, word.to_string())),
+            }
+        }
+
+        if indexes.len() < MIN_WORDS {
+            bail!(ErrorKind::InvalidSlip39Share(format!(
+                "expected at least {} words, got {}",
+                MIN_WORDS,
+                indexes.len()
+            )));
+        }
+
+        let extendable = indexes[1] & (1 << 4) != 0;
+
+        if !rs1024::verify(customization(extendable), &indexes) {
+            if let Some((position, index)) = find_wrong_word(&mut indexes) {
+                bail!(ErrorKind::WrongMnemonicWord(
+                    position + 1,
+                    words[position].to_string(),
+                    list[index as usize].to_string()
+                ));
+            }
+
+            bail!(ErrorKind::MnemonicChecksumMismatch);
+        }
+
+        let id_exp = (u32::from(indexes[0]) << 10) | u32::from(indexes[1]);
+        let params = (u32::from(indexes[2]) << 10) | u32::from(indexes[3]);
+        let nibble = |i: u32| ((params >> (4 * (4 - i))) & 0xF) as u8;
+
+        let value_words = &indexes[HEADER_WORDS..indexes.len() - rs1024::CHECKSUM_WORDS];
+        let value_bits = value_words.len() * BITS_PER_WORD;
+
+        // Secrets are made of an even number of bytes, the padding bits come first
+        let padding = value_bits % 16;
+        if padding > 8 {
+            bail!(ErrorKind::InvalidSlip39Share("invalid length".to_string()));
+        }
+
+        let mut value = vec![0; (value_bits - padding) / 8];
+        for (i, &index) in value_words.iter().enumerate() {
+            for j in 0..BITS_PER_WORD {
+                if index & (1 << (BITS_PER_WORD - 1 - j)) == 0 {
+                    continue;
+                }
+
+                let bit = i * BITS_PER_WORD + j;
+                if bit < padding {
+                    bail!(ErrorKind::InvalidSlip39Share("invalid padding".to_string()));
+                }
+
+                let bit = bit - padding;
+                value[bit / 8] |= 0x80 >> (bit % 8);
+            }
+        }
+
+        let share = Share {
+            identifier: (id_exp >> 5) as u16,
+            extendable,
+            iteration_exponent: (id_exp & 0xF) as u8,
+            group_index: nibble(0),
+            group_threshold: nibble(1) + 1,
+            group_count: nibble(2) + 1,
+            member_index: nibble(3),
+            member_threshold: nibble(4) + 1,
+            value,
+        };
+
+        if share.group_threshold > share.group_count {
+            bail!(ErrorKind::InvalidSlip39Share(
+                "the group threshold is greater than the group count".to_string()
+            ));
+        }
+
+        Ok(share)
+    }
+
+    /// Encodes the share as a mnemonic.
+    pub fn to_words(&self) -> Vec<&'static str> {
+        let list = word_list();
+
+        let id_exp = (u32::from(self.identifier) << 5) | (u32::from(self.extendable) << 4)
+            | u32::from(self.iteration_exponent);
+        let params = [
+            self.group_index,
+            self.group_threshold - 1,
+            self.group_count - 1,
+            self.member_index,
+            self.member_threshold - 1,
+        ].iter()
+            .fold(0, |params, &nibble| (params << 4) | u32::from(nibble));
+
+        let mut indexes = vec![
+            (id_exp >> 10) as u16,
+            (id_exp & 0x3FF) as u16,
+            (params >> 10) as u16,
+            (params & 0x3FF) as u16,
+        ];
+
+        let value_bits = self.value.len() * 8;
+        let padding = (BITS_PER_WORD - value_bits % BITS_PER_WORD) % BITS_PER_WORD;
+
+        for i in 0..(padding + value_bits) / BITS_PER_WORD {
+            let index = (0..BITS_PER_WORD).fold(0, |index, j| {
+                let bit = i * BITS_PER_WORD + j;
+                let set = bit >= padding && self.value[(bit - padding) / 8] & (0x80 >> ((bit - padding) % 8)) != 0;
+                (index << 1) | u16::from(set)
+            });
+            indexes.push(index);
+        }
+
+        let checksum = rs1024::checksum(customization(self.extendable), &indexes);
+        indexes.extend(checksum);
+
+        indexes.into_iter().map(|index| list[index as usize]).collect()
+    }
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable {
+        CUSTOMIZATION_EXTENDABLE
+    } else {
+        CUSTOMIZATION
+    }
+}
+
+/// Looks for the only word which, once replaced, makes the checksum match.
+fn find_wrong_word(indexes: &mut [u16]) -> Option<(usize, u16)> {
+    let mut found = None;
+
+    for position in 0..indexes.len() {
+        let original = indexes[position];
+
+        for candidate in 0..(1 << BITS_PER_WORD) {
+            indexes[position] = candidate;
+
+            let extendable = indexes[1] & (1 << 4) != 0;
+            if candidate != original && rs1024::verify(customization(extendable), indexes) {
+                if found.is_some() {
+                    indexes[position] = original;
+                    return None;
+                }
+                found = Some((position, candidate));
+            }
+        }
+
+        indexes[position] = original;
+    }
+
+    found
+}
+
+/// Encrypts the master secret with the passphrase, and splits it into groups of shares.
+pub fn split(master_secret: &[u8], params: &Params) -> Result<Vec<Vec<Share>>> {
+    if master_secret.len() < MIN_SECRET_LEN || master_secret.len() & 1 != 0 {
+        bail!(ErrorKind::CannotSplitSlip39(format!(
+            "the secret must be an even number of bytes, and at least {} bytes long, got {} bytes",
+            MIN_SECRET_LEN,
+            master_secret.len()
+        )));
+    }
+
+    check_passphrase(&params.passphrase)?;
+
+    let group_count = params.groups.len();
+
+    if group_count == 0 || group_count > MAX_SHARE_COUNT as usize {
+        bail!(ErrorKind::CannotSplitSlip39(format!(
+            "expected between 1 and {} groups, got {}",
+            MAX_SHARE_COUNT, group_count
+        )));
+    }
+
+    if params.group_threshold == 0 || params.group_threshold as usize > group_count {
+        bail!(ErrorKind::CannotSplitSlip39(format!(
+            "the group threshold must be between 1 and the number of groups ({}), got {}",
+            group_count, params.group_threshold
+        )));
+    }
+
+    for &(threshold, count) in &params.groups {
+        if threshold == 0 || threshold > count || count > MAX_SHARE_COUNT {
+            bail!(ErrorKind::CannotSplitSlip39(format!(
+                "invalid group {}/{}, expected at most {} shares per group",
+                threshold, count, MAX_SHARE_COUNT
+            )));
+        }
+
+        if threshold == 1 && count > 1 {
+            bail!(ErrorKind::CannotSplitSlip39(format!(
+                "a group with a threshold of 1 can only have 1 share, got {}",
+                count
+            )));
+        }
+    }
+
+    let mut identifier = [0; 2];
+    random_fill(&mut identifier)?;
+    let identifier = ((u16::from(identifier[0]) << 8) | u16::from(identifier[1])) & 0x7FFF;
+
+    let encrypted = cipher::encrypt(
+        master_secret,
+        params.passphrase.as_bytes(),
+        params.iteration_exponent,
+        identifier,
+        false,
+    );
+
+    let group_secrets = split_secret(params.group_threshold, group_count as u8, &encrypted)?;
+
+    let mut groups = Vec::with_capacity(group_count);
+
+    for (&(member_threshold, member_count), (group_index, group_secret)) in params.groups.iter().zip(group_secrets) {
+        let members = split_secret(member_threshold, member_count, &group_secret)?
+            .into_iter()
+            .map(|(member_index, value)| Share {
+                identifier,
+                extendable: false,
+                iteration_exponent: params.iteration_exponent,
+                group_index,
+                group_threshold: params.group_threshold,
+                group_count: group_count as u8,
+                member_index,
+                member_threshold,
+                value,
+            })
+            .collect();
+
+        groups.push(members);
+    }
+
+    Ok(groups)
+}
+
+/// Recovers the master secret from the shares, and decrypts it with the passphrase.
+/// Shares beyond the thresholds are ignored, so that a whole set can be given at once.
+pub fn combine(shares: &[Share], passphrase: &str) -> Result<Vec<u8>> {
+    check_passphrase(passphrase)?;
+
+    let first = match shares.first() {
+        Some(first) => first,
+        None => bail!(ErrorKind::InvalidSlip39Shares("no share given".to_string())),
+    };
+
+    let mut groups = BTreeMap::new();
+
+    for share in shares {
+        let common = |share: &Share| {
+            (
+                share.identifier,
+                share.extendable,
+                share.iteration_exponent,
+                share.group_threshold,
+                share.group_count,
+            )
+        };
+
+        if common(share) != common(first) {
+            bail!(ErrorKind::InvalidSlip39Shares(
+                "the shares do not all belong to the same secret".to_string()
+            ));
+        }
+
+        let members = groups.entry(share.group_index).or_insert_with(BTreeMap::new);
+
+        if let Some(other) = members.values().next().map(|other: &&Share| other.member_threshold) {
+            if other != share.member_threshold {
+                bail!(ErrorKind::InvalidSlip39Shares(format!(
+                    "the shares of group #{} have different member thresholds",
+                    share.group_index + 1
+                )));
+            }
+        }
+
+        if let Some(other) = members.insert(share.member_index, share) {
+            if other != share {
+                bail!(ErrorKind::InvalidSlip39Shares(format!(
+                    "two different shares have the index #{} in group #{}",
+                    share.member_index + 1,
+                    share.group_index + 1
+                )));
+            }
+        }
+    }
+
+    let mut group_secrets = Vec::new();
+    let mut missing = Vec::new();
+
+    for (&group_index, members) in &groups {
+        let threshold = members.values().next().map_or(0, |share| share.member_threshold) as usize;
+
+        if members.len() < threshold {
+            missing.push(format!(
+                "group #{} needs {} more share(s)",
+                group_index + 1,
+                threshold - members.len()
+            ));
+            continue;
+        }
+
+        let points = members
+            .values()
+            .take(threshold)
+            .map(|share| (share.member_index, share.value.clone()))
+            .collect::<Vec<_>>();
+
+        group_secrets.push((group_index, recover_secret(&points)?));
+    }
+
+    let group_threshold = first.group_threshold as usize;
+
+    if group_secrets.len() < group_threshold {
+        let mut reason = format!(
+            "{} group(s) out of {} are complete, {} are needed",
+            group_secrets.len(),
+            first.group_count,
+            group_threshold
+        );
+
+        for group in missing {
+            reason.push_str(", ");
+            reason.push_str(&group);
+        }
+
+        bail!(ErrorKind::NotEnoughSlip39Shares(reason));
+    }
+
+    group_secrets.truncate(group_threshold);
+
+    let encrypted = recover_secret(&group_secrets)?;
+
+    Ok(cipher::decrypt(
+        &encrypted,
+        passphrase.as_bytes(),
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+    ))
+}
+
+fn check_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.bytes().any(|c| !(32..=126).contains(&c)) {
+        bail!(ErrorKind::InvalidSlip39Passphrase);
+    }
+
+    Ok(())
+}
+
+/// Splits a secret into `count` points, any `threshold` of which recover it.
+/// The polynomial also goes through a digest of the secret, which is checked when recovering.
+fn split_secret(threshold: u8, count: u8, secret: &[u8]) -> Result<Vec<(u8, Vec<u8>)>> {
+    if threshold == 1 {
+        return Ok((0..count).map(|i| (i, secret.to_vec())).collect());
+    }
+
+    let random_count = threshold - 2;
+    let mut points = Vec::with_capacity(count as usize);
+
+    for i in 0..random_count {
+        let mut value = vec![0; secret.len()];
+        random_fill(&mut value)?;
+        points.push((i, value));
+    }
+
+    let mut random_part = vec![0; secret.len() - DIGEST_LEN];
+    random_fill(&mut random_part)?;
+
+    let mut digest = create_digest(&random_part, secret);
+    digest.extend_from_slice(&random_part);
+
+    let mut base = points.clone();
+    base.push((DIGEST_INDEX, digest));
+    base.push((SECRET_INDEX, secret.to_vec()));
+
+    for i in random_count..count {
+        points.push((i, interpolate(&base, i)));
+    }
+
+    Ok(points)
+}
+
+fn recover_secret(points: &[(u8, Vec<u8>)]) -> Result<Vec<u8>> {
+    if points.len() == 1 {
+        return Ok(points[0].1.clone());
+    }
+
+    if points.iter().any(|point| point.1.len() != points[0].1.len()) {
+        bail!(ErrorKind::InvalidSlip39Shares(
+            "the shares do not all have the same length".to_string()
+        ));
+    }
+
+    let secret = interpolate(points, SECRET_INDEX);
+    let digest = interpolate(points, DIGEST_INDEX);
+
+    let (digest, random_part) = digest.split_at(DIGEST_LEN);
+
+    if create_digest(random_part, &secret) != digest {
+        bail!(ErrorKind::InvalidSlip39Shares(
+            "the digest of the recovered secret does not match".to_string()
+        ));
+    }
+
+    Ok(secret)
+}
+
+fn interpolate(points: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    let gf256 = Gf256::rijndael();
+
+    (0..points[0].1.len())
+        .map(|i| {
+            let points = points.iter().map(|&(xj, ref yj)| (xj, yj[i])).collect::<Vec<_>>();
+            gf256.interpolate_at(&points, x)
+        })
+        .collect()
+}
+
+fn create_digest(random_part: &[u8], secret: &[u8]) -> Vec<u8> {
+    let key = hmac::SigningKey::new(&SHA256, random_part);
+    hmac::sign(&key, secret).as_ref()[..DIGEST_LEN].to_vec()
+}
+
+fn random_fill(dest: &mut [u8]) -> Result<()> {
+    SystemRandom::new()
+        .fill(dest)
+        .map_err(|_| ErrorKind::CannotGenerateRandomBytes.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The official vectors in tests/resources do not cover extendable shares, which `split` never makes
+    fn extendable_shares(secret: &[u8], identifier: u16) -> Vec<Share> {
+        let encrypted = cipher::encrypt(secret, b"TREZOR", 1, identifier, true);
+
+        split_secret(2, 3, &encrypted)
+            .unwrap()
+            .into_iter()
+            .map(|(member_index, value)| Share {
+                identifier,
+                extendable: true,
+                iteration_exponent: 1,
+                group_index: 0,
+                group_threshold: 1,
+                group_count: 1,
+                member_index,
+                member_threshold: 2,
+                value,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extendable_shares_round_trip() {
+        let secret = b"0123456789abcdef";
+        let shares = extendable_shares(secret, 0x1234);
+
+        for share in &shares {
+            let words = share.to_words();
+            assert_eq!(&Share::from_words(&words).unwrap(), share);
+        }
+
+        assert_eq!(combine(&shares[1..], "TREZOR").unwrap(), secret);
+        assert_ne!(combine(&shares[1..], "").unwrap(), secret);
+    }
+
+    #[test]
+    fn extendable_encryption_ignores_identifier() {
+        let secret = b"0123456789abcdef";
+        let mut shares = extendable_shares(secret, 0x1234);
+
+        for share in &mut shares {
+            share.identifier = 0x4321;
+        }
+
+        assert_eq!(combine(&shares[..2], "TREZOR").unwrap(), secret);
+    }
+}
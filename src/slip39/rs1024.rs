@@ -0,0 +1,49 @@
+//! The Reed-Solomon checksum over GF(1024) protecting SLIP-39 mnemonics.
+
+const GENERATOR: [u32; 10] = [
+    0x00E0_E040,
+    0x01C1_C080,
+    0x0383_8100,
+    0x0707_0200,
+    0x0E0E_0009,
+    0x1C0C_2412,
+    0x3808_6C24,
+    0x3090_FC48,
+    0x21B1_F890,
+    0x03F3_F120,
+];
+
+/// Number of words taken by the checksum.
+pub const CHECKSUM_WORDS: usize = 3;
+
+fn polymod(customization: &[u8], words: &[u16]) -> u32 {
+    let values = customization.iter().map(|&c| u32::from(c)).chain(words.iter().map(|&w| u32::from(w)));
+
+    values.fold(1, |chk, value| {
+        let top = chk >> 20;
+        let chk = ((chk & 0xF_FFFF) << 10) ^ value;
+
+        GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| (top >> i) & 1 != 0)
+            .fold(chk, |chk, (_, generator)| chk ^ generator)
+    })
+}
+
+/// Computes the checksum words to append to the given words.
+pub fn checksum(customization: &[u8], words: &[u16]) -> Vec<u16> {
+    let mut values = words.to_vec();
+    values.extend_from_slice(&[0; CHECKSUM_WORDS]);
+
+    let polymod = polymod(customization, &values) ^ 1;
+
+    (0..CHECKSUM_WORDS)
+        .map(|i| ((polymod >> (10 * (CHECKSUM_WORDS - 1 - i))) & 1023) as u16)
+        .collect()
+}
+
+/// Checks the words, which end with their checksum.
+pub fn verify(customization: &[u8], words: &[u16]) -> bool {
+    polymod(customization, words) == 1
+}
@@ -0,0 +1,230 @@
+//! Templates of share file names, such as `{{set_id}}/share_{{num1:02}}.{{ext}}`.
+//!
+//! The placeholders are:
+//!
+//! - `{{num}}`: the index of the share minus 1, from 0 as in `share_0`
+//! - `{{num1}}`: the index of the share, from 1 as in its `Index` header
+//! - `{{k}}`: the number of shares necessary to recover the secret
+//! - `{{n}}`: the number of shares, in the group of the share if any
+//! - `{{set_id}}`: the set ID of the shares
+//! - `{{date}}`: the current date, as `YYYY-MM-DD`
+//! - `{{custodian}}`: the name of the custodian of the share
+//! - `{{ext}}`: the usual extension of the format of the share, `asc` or `txt`
+//!
+//! Indexes may be padded with zeros to a given width, such as `{{num:03}}`. The files of
+//! weighted holders are named after them, in place of the index.
+//!
+//! This module is also built into the build script, along with the command line parser
+//! which validates templates with it, so it only depends on `std`.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Num { one_based: bool, width: usize },
+    K,
+    N,
+    SetId,
+    Date,
+    Custodian,
+    Ext,
+}
+
+/// A parsed share file name template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// What the placeholders are replaced with, for a given share.
+pub struct Vars<'a> {
+    /// The identifier of the share, from 1
+    pub id: u8,
+    /// The weighted holder of the share, if any
+    pub holder: Option<&'a str>,
+    pub k: u8,
+    pub n: u8,
+    pub set_id: &'a str,
+    pub date: &'a str,
+    pub custodian: &'a str,
+    pub ext: &'a str,
+}
+
+impl Template {
+    pub fn render(&self, vars: &Vars) -> String {
+        let mut name = String::new();
+
+        for part in &self.parts {
+            let _ = match *part {
+                Part::Text(ref text) => write!(name, "{}", text),
+                Part::Num { one_based, width } => match vars.holder {
+                    Some(holder) => write!(name, "{}", holder),
+                    None if one_based => write!(name, "{:0width$}", vars.id, width = width),
+                    None => write!(name, "{:0width$}", vars.id - 1, width = width),
+                },
+                Part::K => write!(name, "{}", vars.k),
+                Part::N => write!(name, "{}", vars.n),
+                Part::SetId => write!(name, "{}", vars.set_id),
+                Part::Date => write!(name, "{}", vars.date),
+                Part::Custodian => write!(name, "{}", vars.custodian),
+                Part::Ext => write!(name, "{}", vars.ext),
+            };
+        }
+
+        name
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end,
+                None => return Err(format!("unclosed placeholder in '{}'", s)),
+            };
+
+            parts.push(parse_placeholder(&rest[start + 2..end])?);
+            rest = &rest[end + 2..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+
+        // Shares can only be told apart by their index or their custodian
+        let distinct = parts
+            .iter()
+            .any(|part| matches!(*part, Part::Num { .. } | Part::Custodian));
+
+        if !distinct {
+            return Err(
+                "the template must contain {{num}}, {{num1}} or {{custodian}}, so that every share gets its own file"
+                    .to_string(),
+            );
+        }
+
+        Ok(Template { parts })
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, spec) = match placeholder.find(':') {
+        Some(colon) => (&placeholder[..colon], Some(&placeholder[colon + 1..])),
+        None => (placeholder, None),
+    };
+
+    let part = match name {
+        "num" | "num1" => {
+            let width = match spec {
+                Some(spec) if spec.starts_with('0') && spec.len() > 1 => spec[1..]
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid padding in '{{{{{}}}}}', expected :0N", placeholder))?,
+                Some(_) => return Err(format!("invalid padding in '{{{{{}}}}}', expected :0N", placeholder)),
+                None => 0,
+            };
+
+            return Ok(Part::Num {
+                one_based: name == "num1",
+                width,
+            });
+        }
+        "k" => Part::K,
+        "n" => Part::N,
+        "set_id" => Part::SetId,
+        "date" => Part::Date,
+        "custodian" => Part::Custodian,
+        "ext" => Part::Ext,
+        _ => return Err(format!("unknown placeholder '{{{{{}}}}}'", placeholder)),
+    };
+
+    if spec.is_some() {
+        return Err(format!("only indexes may be padded, in '{{{{{}}}}}'", placeholder));
+    }
+
+    Ok(part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars<'a>(id: u8, custodian: &'a str) -> Vars<'a> {
+        Vars {
+            id,
+            holder: None,
+            k: 2,
+            n: 12,
+            set_id: "43e89a08",
+            date: "2018-01-02",
+            custodian,
+            ext: "asc",
+        }
+    }
+
+    fn render(tmpl: &str, vars: &Vars) -> String {
+        tmpl.parse::<Template>().unwrap().render(vars)
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(render("share_{{num}}", &vars(1, "")), "share_0");
+        assert_eq!(
+            render("{{set_id}}/{{date}}/{{k}}-of-{{n}}_{{num1:03}}.{{ext}}", &vars(10, "")),
+            "43e89a08/2018-01-02/2-of-12_010.asc"
+        );
+        assert_eq!(render("{{custodian}}.{{ext}}", &vars(1, "alice")), "alice.asc");
+        assert_eq!(render("no placeholder but {{num}}", &vars(3, "")), "no placeholder but 2");
+    }
+
+    #[test]
+    fn holders_replace_indexes() {
+        let mut vars = vars(1, "");
+        vars.holder = Some("bob");
+
+        assert_eq!(render("share_{{num:02}}", &vars), "share_bob");
+    }
+
+    #[test]
+    fn no_collisions() {
+        // Every template is given something which tells the shares apart, padded or not
+        for tmpl in &["share_{{num}}", "share_{{num1:02}}", "{{set_id}}/share_{{num:04}}_of_{{n}}"] {
+            let mut names = (1..=12).map(|id| render(tmpl, &vars(id, ""))).collect::<Vec<_>>();
+            names.sort();
+            names.dedup();
+
+            assert_eq!(names.len(), 12, "'{}' gives the same name to several shares", tmpl);
+        }
+    }
+
+    #[test]
+    fn colliding_templates() {
+        for tmpl in &["share", "share_{{k}}_{{n}}", "{{set_id}}/{{date}}.{{ext}}", ""] {
+            assert!(tmpl.parse::<Template>().is_err(), "'{}' should be rejected", tmpl);
+        }
+    }
+
+    #[test]
+    fn malformed_templates() {
+        for tmpl in &[
+            "share_{{num",
+            "share_{{nums}}",
+            "share_{{num:3}}",
+            "share_{{num:0}}",
+            "share_{{num:0x}}",
+            "{{ext:02}}_{{num}}",
+        ] {
+            assert!(tmpl.parse::<Template>().is_err(), "'{}' should be rejected", tmpl);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Weighted holders, who hold several shares of a set, each of which counts as a vote.
+//!
+//! With `alice=2,bob=1,carol=1`, the secret is split into 4 shares, 2 of which go to the
+//! file of `alice`. Each share carries the name of its holder in a `Holder` header.
+
+use errors::*;
+use policy;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Holder {
+    /// The name of the holder, which names the file of their shares
+    pub name: String,
+    /// The number of shares held
+    pub weight: u8,
+}
+
+/// Parses a list of `NAME=WEIGHT` holders, separated by commas.
+pub fn parse(value: &str) -> Result<Vec<Holder>> {
+    let invalid = |reason: String| ErrorKind::InvalidWeights(value.to_string(), reason);
+
+    let mut holders: Vec<Holder> = Vec::new();
+
+    for holder in value.split(',').map(str::trim) {
+        let (name, weight) = match holder.find('=') {
+            Some(eq) => (holder[..eq].trim(), holder[eq + 1..].trim()),
+            None => bail!(invalid(format!("expected NAME=WEIGHT, got '{}'", holder))),
+        };
+
+        if !policy::is_valid_name(name) {
+            bail!(invalid(format!("'{}' may only contain letters, digits, '-' and '_'", name)));
+        }
+
+        let weight = match weight.parse::<u8>() {
+            Ok(weight) if weight > 0 => weight,
+            _ => bail!(invalid(format!("invalid weight '{}'", weight))),
+        };
+
+        if holders.iter().any(|other| other.name == name) {
+            bail!(invalid(format!("'{}' is given twice", name)));
+        }
+
+        holders.push(Holder {
+            name: name.to_string(),
+            weight,
+        });
+    }
+
+    if holders.iter().map(|holder| u32::from(holder.weight)).sum::<u32>() > u32::from(u8::MAX) {
+        bail!(invalid(format!("the weights add up to more than {}", u8::MAX)));
+    }
+
+    Ok(holders)
+}
+
+/// The total number of shares held.
+pub fn total(holders: &[Holder]) -> u8 {
+    holders.iter().map(|holder| holder.weight).sum()
+}
@@ -1,11 +1,20 @@
+// Arguments are passed to the commands as borrowed slices throughout
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 extern crate colored;
 use colored::*;
 
+extern crate png;
+
+extern crate serde_json;
+
 extern crate tempdir;
 use tempdir::TempDir;
 
+use std::fs;
+use std::io::Write;
 use std::str;
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 #[test]
 fn raw() {
@@ -63,3 +72,1595 @@ fn run(raw: &[&str]) {
     assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
     assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
 }
+
+#[test]
+fn inspect() {
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &["--sign"]);
+
+    let share_path = format!("{}/share_1", output_path);
+
+    let inspect_out = Command::new("target/debug/rustysecrets")
+        .arg("inspect")
+        .arg(&share_path)
+        .output()
+        .unwrap();
+
+    let stdout = str::from_utf8(&inspect_out.stdout).unwrap();
+
+    assert!(stdout.starts_with(&share_path));
+    assert!(stdout.contains("Index:     2\n"));
+    assert!(stdout.contains("Threshold: 2\n"));
+    assert!(stdout.lines().any(|line| line.starts_with("    Set ID:    ") && line.len() == 23));
+    assert!(stdout.contains("Mode:      wrapped\n"));
+    assert!(stdout.contains("Format:    text\n"));
+    assert!(stdout.contains("Signed:    yes\n"));
+    assert_eq!(str::from_utf8(&inspect_out.stderr).unwrap(), "");
+
+    // A share #0 would hold the secret itself
+    let share = fs::read_to_string(&share_path).unwrap();
+    fs::write(&share_path, share.replace("\n2-2-", "\n2-0-")).unwrap();
+
+    let inspect_out = Command::new("target/debug/rustysecrets")
+        .arg("inspect")
+        .arg(&share_path)
+        .output()
+        .unwrap();
+
+    assert!(!inspect_out.status.success());
+    assert!(str::from_utf8(&inspect_out.stderr)
+        .unwrap()
+        .contains("invalid share identifier '0'"));
+}
+
+#[test]
+fn verify() {
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 3, 5, &["--sign"]);
+
+    let shares = (0..3)
+        .map(|i| format!("{}/share_{}", output_path, i))
+        .collect::<Vec<_>>();
+
+    let verify_out = Command::new("target/debug/rustysecrets")
+        .arg("verify")
+        .args(&shares)
+        .output()
+        .unwrap();
+
+    assert_eq!(verify_out.status.code(), Some(0));
+    assert_eq!(
+        str::from_utf8(&verify_out.stdout).unwrap(),
+        "SHA-256: 28fae21e1a8d042ecf5a2c53e8b4a9dd511f0d429125dd10d97931bc4addf146\n"
+    );
+
+    let verify_out = Command::new("target/debug/rustysecrets")
+        .arg("verify")
+        .args(&shares[..2])
+        .output()
+        .unwrap();
+
+    assert_eq!(verify_out.status.code(), Some(2));
+    assert_eq!(str::from_utf8(&verify_out.stdout).unwrap(), "");
+}
+
+#[test]
+fn detect_mode() {
+    let secret = include_str!("resources/secret.txt");
+
+    for raw in &[&["--raw"][..], &[][..]] {
+        let tmp = TempDir::new("rustysecrets").unwrap();
+        let output_path = split(&tmp, 2, 3, raw);
+
+        // Strip the headers, as in shares written by earlier versions
+        let shares = (0..2)
+            .map(|i| {
+                let path = format!("{}/share_{}", output_path, i);
+                let share = fs::read_to_string(&path).unwrap();
+                fs::write(&path, share.lines().last().unwrap()).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+
+        let recover_out = Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .args(&shares)
+            .output()
+            .unwrap();
+
+        assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    }
+}
+
+#[test]
+fn mixed_modes() {
+    let raw_tmp = TempDir::new("rustysecrets").unwrap();
+    let raw_path = split(&raw_tmp, 2, 3, &["--raw"]);
+
+    let wrapped_tmp = TempDir::new("rustysecrets").unwrap();
+    let wrapped_path = split(&wrapped_tmp, 2, 3, &[]);
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_0", raw_path))
+        .arg(format!("{}/share_1", wrapped_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), "");
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("Cannot mix raw shares"));
+}
+
+#[test]
+fn mixed_sets() {
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &[]);
+
+    let other_tmp = TempDir::new("rustysecrets").unwrap();
+    let other_path = split(&other_tmp, 2, 3, &[]);
+
+    let share_path = format!("{}/share_0", output_path);
+    let other_share_path = format!("{}/share_1", other_path);
+
+    assert_eq!(set_id(&share_path), set_id(&format!("{}/share_2", output_path)));
+    assert_ne!(set_id(&share_path), set_id(&other_share_path));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(&share_path)
+        .arg(&other_share_path)
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr).unwrap().contains(&format!(
+        "Cannot mix shares from different sets: {} from set {}, {} from set {}",
+        share_path,
+        set_id(&share_path),
+        other_share_path,
+        set_id(&other_share_path)
+    )));
+}
+
+#[test]
+fn manifest() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &["--sign"]);
+
+    let other_tmp = TempDir::new("rustysecrets").unwrap();
+    let other_path = split(&other_tmp, 2, 3, &[]);
+
+    let manifest_path = format!("{}/manifest.json", output_path);
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+    let share = fs::read_to_string(format!("{}/share_0", output_path)).unwrap();
+    let set_id = share.lines().find(|line| line.starts_with("Set-Id: ")).unwrap()[8..].to_string();
+
+    assert!(manifest.contains(&format!("\"set_id\": \"{}\"", set_id)));
+    assert!(manifest.contains("\"k\": 2"));
+    assert!(manifest.contains("\"n\": 3"));
+    assert!(manifest.contains("\"signature_root\": "));
+    for i in 0..3 {
+        assert!(manifest.contains(&format!("\"file\": \"share_{}\"", i)));
+    }
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&["--manifest", &manifest_path])
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    let other_share_path = format!("{}/share_2", other_path);
+    fs::copy(&other_share_path, format!("{}/share_2", output_path)).unwrap();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&["--manifest", &manifest_path])
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr).unwrap().contains(&format!(
+        "Shares are not listed in the manifest: {}/share_2",
+        output_path
+    )));
+
+    // Splitting again to the same directory would replace the manifest and the shares of the set
+    let share = fs::read_to_string(format!("{}/share_0", output_path)).unwrap();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path, "-k", "2", "-n", "3"])
+        .output()
+        .unwrap();
+
+    assert_eq!(split_out.status.code(), Some(1));
+    assert!(str::from_utf8(&split_out.stderr).unwrap().contains(&format!(
+        "Manifest '{}' already exists",
+        manifest_path
+    )));
+    assert_eq!(fs::read_to_string(&manifest_path).unwrap(), manifest);
+    assert_eq!(fs::read_to_string(format!("{}/share_0", output_path)).unwrap(), share);
+}
+
+#[test]
+fn policy() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path])
+        .args(&["--policy", "2 of (exec:2/3, eng:3/5)"])
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+    assert!(fs::metadata(format!("{}/exec/share_2", output_path)).is_ok());
+    assert!(fs::metadata(format!("{}/eng/share_4", output_path)).is_ok());
+
+    let recover = |shares: &[&str]| {
+        Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .args(shares.iter().map(|share| format!("{}/{}", output_path, share)))
+            .output()
+            .unwrap()
+    };
+
+    let recover_out = recover(&["exec/share_0", "exec/share_2", "eng/share_1", "eng/share_3"]);
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("1 more needed out of: eng has 2 of 3 shares"));
+
+    let recover_out = recover(&["exec/share_0", "exec/share_2", "eng/share_1", "eng/share_3", "eng/share_4"]);
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+}
+
+#[test]
+fn weights() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path])
+        .args(&["-k", "3"])
+        .args(&["--weights", "alice=2,bob=1,carol=1"])
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+
+    let alice = fs::read_to_string(format!("{}/share_alice", output_path)).unwrap();
+    assert_eq!(alice.matches("Holder: alice").count(), 2);
+
+    let recover = |holders: &[&str]| {
+        Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .args(holders.iter().map(|holder| format!("{}/share_{}", output_path, holder)))
+            .output()
+            .unwrap()
+    };
+
+    let recover_out = recover(&["alice", "carol"]);
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    let recover_out = recover(&["bob", "carol"]);
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("Not enough votes to recover the secret, 1 more needed: got 2 of 3 from bob (1), carol (1)"));
+}
+
+#[test]
+fn custodians() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let alice_tmp = TempDir::new("rustysecrets").unwrap();
+    let alice_path = alice_tmp.path().to_string_lossy().as_ref().to_string();
+
+    let custodians_path = format!("{}/custodians.json", output_path);
+    fs::write(
+        &custodians_path,
+        format!(
+            "[{{\"name\": \"Alice\", \"contact\": \"alice@example.com\", \"path\": \"{}\"}}, \
+             {{\"name\": \"Bob\"}}, {{\"name\": \"Carol\"}}]",
+            alice_path
+        ),
+    ).unwrap();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path])
+        .args(&["-k", "2"])
+        .args(&["--custodians", &custodians_path])
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+    assert!(fs::metadata(format!("{}/share_0", output_path)).is_err());
+
+    let alice_share_path = format!("{}/share_0", alice_path);
+    let alice_share = fs::read_to_string(&alice_share_path).unwrap();
+    assert!(alice_share.contains("Custodian: Alice\n"));
+    assert!(alice_share.contains("Contact: alice@example.com\n"));
+
+    for args in &[&[][..], &["--find-bad"][..]] {
+        let recover_out = Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .arg(&alice_share_path)
+            .arg(format!("{}/share_2", output_path))
+            .args(*args)
+            .output()
+            .unwrap();
+
+        let stderr = str::from_utf8(&recover_out.stderr).unwrap();
+
+        assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+        assert!(stderr.contains("Custodians who took part: Alice, Carol"));
+        assert!(stderr.contains("Custodians who were absent: Bob"));
+    }
+
+    // Names must not take the shares out of the output directory through the share template
+    let escape_tmp = TempDir::new("rustysecrets").unwrap();
+    let escape_path = escape_tmp.path().to_string_lossy().as_ref().to_string();
+
+    for name in &["../escaped", "a/b", "a\\\\b", "..", ".hidden"] {
+        fs::write(
+            &custodians_path,
+            format!("[{{\"name\": \"Alice\"}}, {{\"name\": \"{}\"}}]", name),
+        ).unwrap();
+
+        let split_out = Command::new("target/debug/rustysecrets")
+            .arg("split")
+            .arg("tests/resources/secret.txt")
+            .args(&["-o", &escape_path])
+            .args(&["-k", "2"])
+            .args(&["-t", "{{custodian}}_share"])
+            .args(&["--custodians", &custodians_path])
+            .output()
+            .unwrap();
+
+        assert_eq!(split_out.status.code(), Some(1), "'{}' should be rejected", name);
+        assert!(str::from_utf8(&split_out.stderr)
+            .unwrap()
+            .contains("may only contain letters, digits, '-' and '_'"));
+    }
+
+    assert_eq!(fs::read_dir(&escape_path).unwrap().count(), 0);
+    assert!(!escape_tmp.path().parent().unwrap().join("escaped_share").exists());
+}
+
+#[test]
+fn share_tmpl() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &["-f", "armor", "-t", "{{k}}-of-{{n}}/share_{{num1:02}}.{{ext}}"]);
+
+    let shares = (1..4)
+        .map(|i| format!("{}/2-of-3/share_{:02}.asc", output_path, i))
+        .collect::<Vec<_>>();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&shares[1..])
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path])
+        .args(&["-k", "2", "-n", "3"])
+        .args(&["-t", "share_{{nmu}}"])
+        .output()
+        .unwrap();
+
+    assert!(!split_out.status.success());
+    assert!(str::from_utf8(&split_out.stderr)
+        .unwrap()
+        .contains("unknown placeholder '{{nmu}}'"));
+
+    let custodians_path = format!("{}/custodians", output_path);
+    fs::create_dir(&custodians_path).unwrap();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &custodians_path])
+        .args(&["-k", "2", "-n", "3"])
+        .args(&["-t", "share_{{custodian}}"])
+        .output()
+        .unwrap();
+
+    assert_eq!(split_out.status.code(), Some(1));
+    assert!(str::from_utf8(&split_out.stderr)
+        .unwrap()
+        .contains("Shares #1 and #2 would both be written to"));
+}
+
+#[test]
+fn recipients() {
+    let secret = include_str!("resources/secret.txt");
+
+    let identity = "AGE-SECRET-KEY-1MZWT466SQFN00MK6NDW4QLELWVDQY2WPQMEDEXN5XPWUY8HCSDAQFMYLZC";
+    let recipient = "age1ajmwezr94kv3lw3hw2ydh7q6r2renrmjvegr5052z2z55xvhdc7sch2gxy";
+
+    let keys_tmp = TempDir::new("rustysecrets").unwrap();
+    let identity_path = keys_tmp.path().join("identity.txt");
+    let recipients_path = keys_tmp.path().join("recipients.txt");
+    fs::write(&identity_path, format!("# public key: {}\n{}\n", recipient, identity)).unwrap();
+    fs::write(&recipients_path, format!("# Bob\n2={}\n3={}\n", recipient, recipient)).unwrap();
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(
+        &tmp,
+        2,
+        3,
+        &[
+            "--recipient",
+            &format!("1={}", recipient),
+            "--recipients-file",
+            &recipients_path.to_string_lossy(),
+        ],
+    );
+
+    assert!(fs::metadata(format!("{}/share_0", output_path)).is_err());
+
+    let share = fs::read_to_string(format!("{}/share_0.age", output_path)).unwrap();
+    assert!(share.starts_with("-----BEGIN AGE ENCRYPTED FILE-----\n"));
+    assert!(!share.contains("Set-Id"));
+
+    let shares = vec![format!("{}/share_0.age", output_path), format!("{}/share_2.age", output_path)];
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&shares)
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("is encrypted, pass the identity of its recipient with --identity"));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&shares)
+        .arg("--identity")
+        .arg(&identity_path)
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path])
+        .args(&["-k", "2", "-n", "3"])
+        .args(&["--recipient", &format!("1={}", recipient)])
+        .output()
+        .unwrap();
+
+    assert_eq!(split_out.status.code(), Some(1));
+    assert!(str::from_utf8(&split_out.stderr)
+        .unwrap()
+        .contains("no recipient for shares #2, #3"));
+
+    // Shares are numbered from 1, as in their Index header
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path])
+        .args(&["-k", "2", "-n", "3"])
+        .args(&["--recipient", &format!("0={}", recipient)])
+        .output()
+        .unwrap();
+
+    assert_eq!(split_out.status.code(), Some(1));
+    assert!(str::from_utf8(&split_out.stderr)
+        .unwrap()
+        .contains("there is no share #0 out of 3 shares"));
+}
+
+#[test]
+fn recipients_age_interop() {
+    let secret = include_str!("resources/secret.txt");
+
+    // Shares encrypted by rage 0.12.1, with and without armor, to an identity generated by rage-keygen
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&["--identity", "tests/resources/age-interop/identity.txt"])
+        .arg("tests/resources/age-interop/share_0.age")
+        .arg("tests/resources/age-interop/share_1.age")
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+}
+
+#[test]
+fn interactive() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &[]);
+
+    let other_tmp = TempDir::new("rustysecrets").unwrap();
+    let other_path = split(&other_tmp, 2, 3, &[]);
+
+    // A typo, a share with identifier 0, a share of another set, the same share twice, and enough shares
+    let input = [
+        fs::read_to_string(format!("{}/share_0", output_path)).unwrap(),
+        "2-2-typo\n".to_string(),
+        fs::read_to_string(format!("{}/share_1", output_path)).unwrap().replace("\n2-2-", "\n2-0-"),
+        fs::read_to_string(format!("{}/share_1", other_path)).unwrap(),
+        fs::read_to_string(format!("{}/share_0", output_path)).unwrap(),
+        fs::read_to_string(format!("{}/share_2", output_path)).unwrap(),
+    ].join("\n");
+
+    let mut recover = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg("--interactive")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    recover.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let recover_out = recover.wait_with_output().unwrap();
+
+    let stderr = str::from_utf8(&recover_out.stderr).unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert!(stderr.contains("Share #1 is valid, 1 more needed"));
+    assert!(stderr.contains("Invalid share, try again"));
+    assert!(stderr.contains("invalid share identifier '0'"));
+    assert!(stderr.contains("Share #2 does not belong to the same set as the first share"));
+    assert!(stderr.contains("Share #1 was already entered"));
+    assert!(stderr.contains("Got 2 shares, recovering the secret"));
+
+    let mut recover = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg("--interactive")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let share = fs::read_to_string(format!("{}/share_1", output_path)).unwrap();
+    recover.stdin.take().unwrap().write_all(share.as_bytes()).unwrap();
+    let recover_out = recover.wait_with_output().unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("Not enough shares to recover the secret, got 1 of 2"));
+}
+
+#[test]
+fn shares_dir_and_pattern() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &[]);
+
+    // Files which are not shares are skipped, along with the manifest
+    fs::write(format!("{}/notes.txt", output_path), "garbage").unwrap();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(&output_path)
+        .output()
+        .unwrap();
+
+    let stderr = str::from_utf8(&recover_out.stderr).unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert!(stderr.contains(&format!("Cannot parse share at '{}/notes.txt', skipping it", output_path)));
+    assert!(!stderr.contains("manifest.json"));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_[12]", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/nothing_*", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains(&format!("No share found in '{}/nothing_*'", output_path)));
+
+    // Text shares piped on consecutive lines, without their headers
+    let input = (0..2)
+        .map(|i| {
+            let share = fs::read_to_string(format!("{}/share_{}", output_path, i)).unwrap();
+            format!("{}\n", share.lines().last().unwrap())
+        })
+        .collect::<String>();
+
+    let mut recover = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    recover.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let recover_out = recover.wait_with_output().unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+}
+
+#[test]
+fn secret_sources() {
+    let secret = include_str!("resources/secret.txt");
+
+    let recover = |output_path: &str| {
+        let recover_out = Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .arg(format!("{}/share_0", output_path))
+            .arg(format!("{}/share_1", output_path))
+            .output()
+            .unwrap();
+
+        assert!(recover_out.status.success());
+        recover_out.stdout
+    };
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .args(&["split", "--secret-env", "RUSTYSECRETS_SECRET"])
+        .args(&["-o", &output_path, "-k", "2", "-n", "3"])
+        .env("RUSTYSECRETS_SECRET", secret)
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+    assert_eq!(recover(&output_path), secret.as_bytes());
+
+    // The value of a missing variable cannot be leaked, but its name is told
+    let split_out = Command::new("target/debug/rustysecrets")
+        .args(&["split", "--secret-env", "RUSTYSECRETS_MISSING"])
+        .args(&["-o", &output_path, "-k", "2", "-n", "3"])
+        .env_remove("RUSTYSECRETS_MISSING")
+        .output()
+        .unwrap();
+
+    assert_eq!(split_out.status.code(), Some(1));
+    assert!(str::from_utf8(&split_out.stderr)
+        .unwrap()
+        .contains("Cannot open secret from '$RUSTYSECRETS_MISSING'"));
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "target/debug/rustysecrets split --secret-fd 3 -o {} -k 2 -n 3 3< tests/resources/secret.txt",
+            output_path
+        ))
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+    assert_eq!(recover(&output_path), secret.as_bytes());
+
+    for &(kind, check) in &[
+        ("hex:64", (|s: &[u8]| s.len() == 64 && s.iter().all(u8::is_ascii_hexdigit)) as fn(&[u8]) -> bool),
+        ("base58:22", |s: &[u8]| s.len() == 22 && !s.iter().any(|c| b"0OIl".contains(c))),
+        ("words:12", |s: &[u8]| s.split(|&c| c == b' ').count() == 12),
+        ("bytes:32", |s: &[u8]| s.len() == 32),
+    ] {
+        let tmp = TempDir::new("rustysecrets").unwrap();
+        let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+        let split_out = Command::new("target/debug/rustysecrets")
+            .args(&["split", "--generate", kind, "--raw"])
+            .args(&["-o", &output_path, "-k", "2", "-n", "3"])
+            .output()
+            .unwrap();
+
+        assert!(split_out.status.success());
+        assert!(check(&recover(&output_path)), "{}", kind);
+    }
+}
+
+#[test]
+fn generate() {
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .args(&["split", "--generate", "hex:64", "--public-key", "ed25519", "--raw"])
+        .args(&["-o", &output_path, "-k", "2", "-n", "3"])
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+
+    // Only the fingerprint and the public key are printed, never the secret
+    let stdout = str::from_utf8(&split_out.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("Fingerprint: "));
+    assert!(lines[1].starts_with("Ed25519 public key: "));
+    assert_eq!(lines[1].len(), "Ed25519 public key: ".len() + 64);
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .output()
+        .unwrap();
+
+    let secret = str::from_utf8(&recover_out.stdout).unwrap();
+
+    assert_eq!(secret.len(), 64);
+    assert!(!stdout.contains(secret));
+
+    let sha256_out = Command::new("sh")
+        .arg("-c")
+        .arg(format!("printf %s {} | sha256sum", secret))
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        &str::from_utf8(&sha256_out.stdout).unwrap()[..64],
+        &lines[0]["Fingerprint: ".len()..]
+    );
+
+    // Only 32 byte seeds make Ed25519 keys, which is checked before writing any share
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .args(&["split", "--generate", "16", "--public-key", "ed25519"])
+        .args(&["-o", &output_path, "-k", "2", "-n", "3"])
+        .output()
+        .unwrap();
+
+    assert_eq!(split_out.status.code(), Some(1));
+    assert!(str::from_utf8(&split_out.stderr)
+        .unwrap()
+        .contains("Cannot derive a public key from a 16 byte secret"));
+    assert_eq!(fs::read_dir(&output_path).unwrap().count(), 0);
+
+    // Secrets which could be guessed from their fingerprint are never generated
+    let kinds = [("15", "bytes:16"), ("hex:31", "hex:32"), ("base58:21", "base58:22"), ("words:11", "words:12")];
+    for &(kind, shortest) in &kinds {
+        let split_out = Command::new("target/debug/rustysecrets")
+            .args(&["split", "--generate", kind])
+            .args(&["-o", &output_path, "-k", "2", "-n", "3"])
+            .output()
+            .unwrap();
+
+        assert_eq!(split_out.status.code(), Some(1));
+        assert!(str::from_utf8(&split_out.stderr).unwrap().contains(&format!(
+            "'{}' holds less than 128 bits of entropy, expected at least {}",
+            kind, shortest
+        )));
+    }
+    assert_eq!(fs::read_dir(&output_path).unwrap().count(), 0);
+}
+
+#[test]
+fn passphrase_protect() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .env("SHARE_PASSPHRASE", "correct horse")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(&["-o", &output_path])
+        .args(&["-k", "2", "-n", "3"])
+        .arg("--passphrase-protect")
+        .args(&["--share-passphrase-env", "SHARE_PASSPHRASE"])
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+
+    let share = fs::read_to_string(format!("{}/share_0", output_path)).unwrap();
+    assert!(share.starts_with("-----BEGIN RUSTYSECRETS PROTECTED SHARE-----\nKdf: argon2id\n"));
+
+    let keys_tmp = TempDir::new("rustysecrets").unwrap();
+    let passphrases_path = keys_tmp.path().join("passphrases.txt");
+    fs::write(&passphrases_path, "correct horse\nbattery staple\n").unwrap();
+
+    let shares = vec![format!("{}/share_0", output_path), format!("{}/share_2", output_path)];
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&shares)
+        .arg("--share-passphrases")
+        .arg(&passphrases_path)
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains(&format!("Wrong passphrase for {}/share_2\n", output_path)));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .env("SHARE_PASSPHRASE", "correct horse")
+        .arg("recover")
+        .args(&shares)
+        .args(&["--share-passphrase-env", "SHARE_PASSPHRASE"])
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    // Line endings may have been converted on the way
+    fs::write(&shares[1], fs::read_to_string(&shares[1]).unwrap().replace("\n", "\r\n")).unwrap();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .env("SHARE_PASSPHRASE", "correct horse")
+        .arg("recover")
+        .args(&shares)
+        .args(&["--share-passphrase-env", "SHARE_PASSPHRASE"])
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    // A share must not make us derive its key with arbitrary amounts of memory or time
+    fs::write(&shares[0], share.replace("Memory: 65536\n", "Memory: 4294967295\n")).unwrap();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .env("SHARE_PASSPHRASE", "correct horse")
+        .arg("recover")
+        .args(&shares)
+        .args(&["--share-passphrase-env", "SHARE_PASSPHRASE"])
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr).unwrap().contains(
+        "Protected share asks for too costly a key derivation: 4294967295 KiB of memory, 3 iterations"
+    ));
+}
+
+#[test]
+fn find_bad() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 3, 6, &[]);
+
+    let other_tmp = TempDir::new("rustysecrets").unwrap();
+    let other_path = split(&other_tmp, 3, 6, &[]);
+
+    fs::copy(
+        format!("{}/share_2", other_path),
+        format!("{}/share_2", output_path),
+    ).unwrap();
+    fs::write(format!("{}/share_4", output_path), "garbage").unwrap();
+
+    let shares = (0..6)
+        .map(|i| format!("{}/share_{}", output_path, i))
+        .collect::<Vec<_>>();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&shares)
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg("--find-bad")
+        .args(&shares)
+        .output()
+        .unwrap();
+
+    let stderr = str::from_utf8(&recover_out.stderr).unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert!(stderr.contains(&format!("Cannot parse share at '{}/share_4'", output_path)));
+    assert!(stderr.contains(&format!("Share '{}/share_2' is inconsistent", output_path)));
+}
+
+#[test]
+fn find_bad_many_shares() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 15, 30, &[]);
+
+    let other_tmp = TempDir::new("rustysecrets").unwrap();
+    let other_path = split(&other_tmp, 15, 30, &[]);
+
+    let shares = (0..30)
+        .map(|i| format!("{}/share_{}", output_path, i))
+        .collect::<Vec<_>>();
+
+    // Too many subsets to try them all, so random ones are tried until one is proven right
+    for i in 0..2 {
+        fs::copy(
+            format!("{}/share_{}", other_path, i),
+            format!("{}/share_{}", output_path, i),
+        ).unwrap();
+    }
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg("--find-bad")
+        .args(&shares)
+        .output()
+        .unwrap();
+
+    let stderr = str::from_utf8(&recover_out.stderr).unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert!(stderr.contains(&format!("Share '{}/share_0' is inconsistent", output_path)));
+    assert!(stderr.contains(&format!("Share '{}/share_1' is inconsistent", output_path)));
+
+    // With too few good shares to be sure of any subset, the search gives up
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 5, 16, &[]);
+
+    let other_tmp = TempDir::new("rustysecrets").unwrap();
+    let other_path = split(&other_tmp, 5, 16, &[]);
+
+    for i in 0..10 {
+        fs::copy(
+            format!("{}/share_{}", other_path, i),
+            format!("{}/share_{}", output_path, i),
+        ).unwrap();
+    }
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg("--find-bad")
+        .arg(&output_path)
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("Gave up looking for consistent shares after"));
+}
+
+#[test]
+fn reshare() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &["-m", "text/plain"]);
+
+    let reshare_tmp = TempDir::new("rustysecrets").unwrap();
+    let reshare_path = reshare_tmp.path().to_string_lossy().as_ref().to_string();
+
+    let reshare_out = Command::new("target/debug/rustysecrets")
+        .arg("reshare")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .args(&["-o", &reshare_path])
+        .args(&["-k", "3"])
+        .args(&["-n", "5"])
+        .args(&["-t", "new_{{num}}"])
+        .output()
+        .unwrap();
+
+    assert!(reshare_out.status.success());
+
+    let shares = (2..5)
+        .map(|i| format!("{}/new_{}", reshare_path, i))
+        .collect::<Vec<_>>();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg("-v")
+        .args(&shares)
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("MIME-Type: text/plain"));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&shares[..2])
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+
+    // The new set has its own manifest, which the old shares are not listed in
+    let manifest_path = format!("{}/manifest.json", reshare_path);
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+
+    assert!(manifest.contains(&format!("\"set_id\": \"{}\"", set_id(&shares[0]))));
+    assert_ne!(set_id(&shares[0]), set_id(&format!("{}/share_0", output_path)));
+    assert!(manifest.contains("\"k\": 3"));
+    assert!(manifest.contains("\"n\": 5"));
+    assert!(manifest.contains("\"mime_type\": \"text/plain\""));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&["--manifest", &manifest_path])
+        .args(&shares)
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&["--manifest", &manifest_path])
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(recover_out.status.code(), Some(1));
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains("Shares are not listed in the manifest"));
+
+    // Resharing next to the old shares would replace their manifest
+    let reshare_out = Command::new("target/debug/rustysecrets")
+        .arg("reshare")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .args(&["-o", &output_path])
+        .args(&["-k", "3", "-n", "5", "-t", "new_{{num}}"])
+        .output()
+        .unwrap();
+
+    assert_eq!(reshare_out.status.code(), Some(1));
+    assert!(str::from_utf8(&reshare_out.stderr)
+        .unwrap()
+        .contains(&format!("Manifest '{}/manifest.json' already exists", output_path)));
+    assert!(fs::metadata(format!("{}/new_0", output_path)).is_err());
+}
+
+#[test]
+fn extend() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &[]);
+
+    let extend_out = Command::new("target/debug/rustysecrets")
+        .arg("extend")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .args(&["-o", &output_path])
+        .output()
+        .unwrap();
+
+    assert!(extend_out.status.success());
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_2", output_path))
+        .arg(format!("{}/share_3", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    let extend_out = Command::new("target/debug/rustysecrets")
+        .arg("extend")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .args(&["-o", &output_path])
+        .args(&["-i", "3"])
+        .output()
+        .unwrap();
+
+    assert_eq!(extend_out.status.code(), Some(1));
+    assert!(str::from_utf8(&extend_out.stderr)
+        .unwrap()
+        .contains(&format!("Share index 3 is already used by '{}/share_2'", output_path)));
+
+    // The new share was added to the manifest of the set
+    let manifest_path = format!("{}/manifest.json", output_path);
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&["--manifest", &manifest_path])
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_3", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert!(fs::read_to_string(&manifest_path).unwrap().contains("\"n\": 4"));
+
+    let extend_out = Command::new("target/debug/rustysecrets")
+        .arg("extend")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .args(&["-o", &output_path])
+        .args(&["-i", "6", "-i", "6"])
+        .output()
+        .unwrap();
+
+    assert_eq!(extend_out.status.code(), Some(1));
+    assert!(str::from_utf8(&extend_out.stderr)
+        .unwrap()
+        .contains("Share index 6 is given twice"));
+
+    // The shares of another set do not use up the indexes of this one
+    let other_tmp = TempDir::new("rustysecrets").unwrap();
+    let other_path = split(&other_tmp, 2, 5, &[]);
+    fs::copy(format!("{}/share_4", other_path), format!("{}/other_4", output_path)).unwrap();
+
+    let extend_out = Command::new("target/debug/rustysecrets")
+        .arg("extend")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .args(&["-o", &output_path])
+        .args(&["-i", "5"])
+        .output()
+        .unwrap();
+
+    assert!(extend_out.status.success());
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .args(&["--manifest", &manifest_path])
+        .arg(format!("{}/share_2", output_path))
+        .arg(format!("{}/share_4", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+
+    // Extending to a directory with the manifest of another set would mix up both sets
+    let extend_out = Command::new("target/debug/rustysecrets")
+        .arg("extend")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .args(&["-o", &other_path])
+        .output()
+        .unwrap();
+
+    assert_eq!(extend_out.status.code(), Some(1));
+    assert!(str::from_utf8(&extend_out.stderr)
+        .unwrap()
+        .contains(&format!("Manifest '{}/manifest.json' lists the shares of another set", other_path)));
+}
+
+#[test]
+fn encrypt() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let ciphertext_path = tmp.path().join("secret.enc");
+    let output_path = split(&tmp, 2, 3, &["-e", ciphertext_path.to_str().unwrap()]);
+
+    let ciphertext = fs::read(&ciphertext_path).unwrap();
+    assert!(!ciphertext.windows(16).any(|w| secret.as_bytes().starts_with(w)));
+
+    let share = fs::read_to_string(format!("{}/share_0", output_path)).unwrap();
+    assert!(share.contains("Ciphertext: secret.enc\n"));
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+}
+
+#[test]
+fn encrypt_large() {
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    // Exactly three chunks, followed by an empty one
+    let secret = (0..3 * 64 * 1024).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+    let secret_path = tmp.path().join("secret.bin");
+    fs::write(&secret_path, &secret).unwrap();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg(&secret_path)
+        .args(&["-e", &format!("{}/secret.enc", output_path)])
+        .args(&["-o", &output_path])
+        .args(&["-k", "2"])
+        .args(&["-n", "3"])
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+
+    let recovered_path = tmp.path().join("recovered.bin");
+    let recover = || {
+        Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .arg(format!("{}/share_1", output_path))
+            .arg(format!("{}/share_2", output_path))
+            .arg("-o")
+            .arg(&recovered_path)
+            .output()
+            .unwrap()
+    };
+
+    assert!(recover().status.success());
+    assert_eq!(fs::read(&recovered_path).unwrap(), secret);
+
+    // Dropping the last chunk must be detected, and leave no partial secret behind
+    let ciphertext_path = tmp.path().join("secret.enc");
+    let ciphertext = fs::read(&ciphertext_path).unwrap();
+    fs::write(&ciphertext_path, &ciphertext[..ciphertext.len() - 16]).unwrap();
+    fs::remove_file(&recovered_path).unwrap();
+
+    assert!(!recover().status.success());
+    assert!(!recovered_path.exists());
+}
+
+#[test]
+fn mnemonic() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 3, 4, &["--format", "mnemonic"]);
+
+    let share = fs::read_to_string(format!("{}/share_2", output_path)).unwrap();
+    let words = share.lines().skip(2).collect::<Vec<_>>().join("\n");
+    assert!(words.split_whitespace().all(|word| word.chars().all(|c| c.is_ascii_lowercase())));
+
+    // Two shares from files, and one typed on stdin without its headers
+    let mut recover = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    recover.stdin.take().unwrap().write_all(words.as_bytes()).unwrap();
+    let recover_out = recover.wait_with_output().unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+
+    // Replace the fifth word with another one
+    let mut typo = words.split_whitespace().collect::<Vec<_>>();
+    let (wrong, right) = (if typo[4] == "zoo" { "zone" } else { "zoo" }, typo[4]);
+    typo[4] = wrong;
+    fs::write(format!("{}/share_2", output_path), typo.join(" ")).unwrap();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_2", output_path))
+        .arg(format!("{}/share_3", output_path))
+        .output()
+        .unwrap();
+
+    let stderr = str::from_utf8(&recover_out.stderr).unwrap();
+    assert!(!recover_out.status.success());
+    assert!(stderr.contains(&format!("word #5 '{}' should probably be '{}'", wrong, right)));
+}
+
+#[test]
+fn armor() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &["--format", "armor", "-m", "text/plain"]);
+
+    let share = fs::read_to_string(format!("{}/share_0", output_path)).unwrap();
+    assert!(share.starts_with("-----BEGIN RUSTYSECRETS SHARE-----\nIndex: 1\nThreshold: 2\n"));
+    assert!(share.contains("\nMime-Type: text/plain\n"));
+    assert!(share.ends_with("-----END RUSTYSECRETS SHARE-----\n"));
+
+    // The text around the armor is ignored
+    fs::write(
+        format!("{}/share_0", output_path),
+        format!("Here is my share:\n\n{}\nRegards", share),
+    ).unwrap();
+
+    let recover = || {
+        Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .arg(format!("{}/share_0", output_path))
+            .arg(format!("{}/share_1", output_path))
+            .arg(format!("{}/share_2", output_path))
+            .output()
+            .unwrap()
+    };
+
+    let recover_out = recover();
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+
+    // The checksum covers the index and threshold of the share
+    let share_1 = fs::read_to_string(format!("{}/share_1", output_path)).unwrap();
+    fs::write(format!("{}/share_1", output_path), share_1.replace("Index: 2", "Index: 3")).unwrap();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .output()
+        .unwrap();
+    assert!(!recover_out.status.success());
+    assert!(str::from_utf8(&recover_out.stderr)
+        .unwrap()
+        .contains(&format!("The checksum does not match in {}/share_1", output_path)));
+
+    fs::write(format!("{}/share_1", output_path), share_1).unwrap();
+
+    // Lose a character of one share, and a line of another
+    for (i, corrupt) in [|l: &str| l[1..].to_string(), |_: &str| String::new()].iter().enumerate() {
+        let path = format!("{}/share_{}", output_path, i + 1);
+        let share = fs::read_to_string(&path).unwrap();
+        let body = share.lines().position(str::is_empty).unwrap() + 1;
+        let lines = share
+            .lines()
+            .enumerate()
+            .map(|(n, line)| if n == body { corrupt(line) } else { line.to_string() })
+            .collect::<Vec<_>>();
+        fs::write(&path, lines.join("\n")).unwrap();
+    }
+
+    let recover_out = recover();
+    assert!(!recover_out.status.success());
+    assert!(str::from_utf8(&recover_out.stderr).unwrap().contains(&format!(
+        "The checksum does not match in {0}/share_1, {0}/share_2",
+        output_path
+    )));
+}
+
+#[test]
+fn qr() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = split(&tmp, 2, 3, &["--qr", "png"]);
+    let qr_tmp = TempDir::new("rustysecrets").unwrap();
+    let qr_path = split(&qr_tmp, 2, 3, &["--qr", "svg", "--qr-only", "-t", "qr_{{num}}"]);
+
+    assert!(fs::metadata(format!("{}/share_0", output_path)).is_ok());
+    assert!(fs::metadata(format!("{}/qr_0", qr_path)).is_err());
+
+    // A share scanned from a PNG image along with a share file, then two shares read from SVG images
+    for (path, shares) in &[(&output_path, ["share_0.png", "share_2"]), (&qr_path, ["qr_1.svg", "qr_2.svg"])] {
+        let recover_out = Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .args(&shares.iter().map(|share| format!("{}/{}", path, share)).collect::<Vec<_>>())
+            .output()
+            .unwrap();
+
+        assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+        assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+    }
+
+    // Scanners and image editors may save the images with 16 bits per sample
+    let image_path = format!("{}/share_1.png", output_path);
+    to_16_bit_png(&image_path);
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(&image_path)
+        .arg(format!("{}/share_2", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+}
+
+/// Rewrites a PNG image with 16 bits per sample.
+fn to_16_bit_png(path: &str) {
+    let (info, mut reader) = png::Decoder::new(fs::File::open(path).unwrap()).read_info().unwrap();
+    let mut data = vec![0; info.buffer_size()];
+    reader.next_frame(&mut data).unwrap();
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+
+    let mut encoder = png::Encoder::new(fs::File::create(path).unwrap(), info.width, info.height);
+    encoder.set_color(info.color_type);
+    encoder.set_depth(png::BitDepth::Sixteen);
+
+    let wide = data.iter().flat_map(|&sample| vec![sample, sample]).collect::<Vec<_>>();
+    encoder.write_header().unwrap().write_image_data(&wide).unwrap();
+}
+
+#[test]
+fn print_pdf() {
+    let secret = include_str!("resources/secret.txt");
+
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let pdf_path = tmp.path().join("cards.pdf");
+    let output_path = split(&tmp, 2, 3, &["--print-pdf", pdf_path.to_str().unwrap(), "--label", "Vault"]);
+
+    let pdf = String::from_utf8_lossy(&fs::read(&pdf_path).unwrap()).into_owned();
+    assert!(pdf.starts_with("%PDF-1.4\n"));
+    assert!(pdf.contains("/Count 3"));
+    assert!(pdf.contains("(Vault) Tj"));
+    assert!(pdf.contains("(2 of 3 shares needed) Tj"));
+
+    // Type a share back as printed, in groups of characters
+    let share = fs::read_to_string(format!("{}/share_1", output_path)).unwrap();
+    let body = share.lines().last().unwrap().chars().collect::<Vec<_>>();
+    let groups = body.chunks(5).map(|group| group.iter().collect::<String>()).collect::<Vec<_>>();
+    fs::write(format!("{}/share_1", output_path), groups.join(" ")).unwrap();
+
+    let recover_out = Command::new("target/debug/rustysecrets")
+        .arg("recover")
+        .arg(format!("{}/share_0", output_path))
+        .arg(format!("{}/share_1", output_path))
+        .output()
+        .unwrap();
+
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+}
+
+#[test]
+fn slip39() {
+    let tmp = TempDir::new("rustysecrets").unwrap();
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let secret = "0123456789abcdef0123456789abcdef";
+    let secret_path = tmp.path().join("secret.txt");
+    fs::write(&secret_path, secret).unwrap();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg(&secret_path)
+        .args(&["-o", &output_path])
+        .args(&["--format", "slip39"])
+        .args(&["--group-threshold", "2"])
+        .args(&["--group", "1/1", "--group", "2/3", "--group", "3/5"])
+        .args(&["--iteration-exponent", "0"])
+        .args(&["--passphrase-env", "RUSTYSECRETS_TEST_PASSPHRASE"])
+        .env("RUSTYSECRETS_TEST_PASSPHRASE", "correct horse")
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+
+    // The only share of the first group, and two shares of the second one
+    let recover = |passphrase: &str| {
+        Command::new("target/debug/rustysecrets")
+            .arg("recover")
+            .arg(format!("{}/share_0", output_path))
+            .arg(format!("{}/share_1", output_path))
+            .arg(format!("{}/share_3", output_path))
+            .args(&["--passphrase-env", "RUSTYSECRETS_TEST_PASSPHRASE"])
+            .env("RUSTYSECRETS_TEST_PASSPHRASE", passphrase)
+            .output()
+            .unwrap()
+    };
+
+    let recover_out = recover("correct horse");
+    assert_eq!(str::from_utf8(&recover_out.stdout).unwrap(), secret);
+    assert_eq!(str::from_utf8(&recover_out.stderr).unwrap(), "");
+
+    // A wrong passphrase silently yields another secret
+    let recover_out = recover("wrong horse");
+    assert!(recover_out.status.success());
+    assert_ne!(str::from_utf8(&recover_out.stdout).unwrap_or(""), secret);
+}
+
+#[test]
+fn slip39_vectors() {
+    // The official vectors.json of python-shamir-mnemonic: a description, the mnemonics, the master secret
+    // in hex, or an empty string if the mnemonics are invalid, and in later revisions the extended private key
+    let vectors: Vec<Vec<serde_json::Value>> =
+        serde_json::from_str(include_str!("resources/slip39_vectors.json")).unwrap();
+
+    for vector in vectors {
+        let description = vector[0].as_str().unwrap();
+        let mnemonics = vector[1]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|mnemonic| mnemonic.as_str().unwrap())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let expected = vector[2].as_str().unwrap();
+
+        let mut recover = Command::new("target/debug/rustysecrets")
+            .args(&["recover", "-"])
+            .args(&["--passphrase-env", "RUSTYSECRETS_TEST_PASSPHRASE"])
+            .env("RUSTYSECRETS_TEST_PASSPHRASE", "TREZOR")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        recover.stdin.take().unwrap().write_all(mnemonics.as_bytes()).unwrap();
+        let recover_out = recover.wait_with_output().unwrap();
+
+        if expected.is_empty() {
+            assert!(!recover_out.status.success(), "{}", description);
+        } else {
+            let recovered = recover_out.stdout.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+            assert_eq!(recovered, expected, "{}", description);
+        }
+    }
+}
+
+fn set_id(share_path: &str) -> String {
+    let share = fs::read_to_string(share_path).unwrap();
+    share.lines().find(|line| line.starts_with("Set-Id: ")).unwrap()[8..].to_string()
+}
+
+fn split(tmp: &TempDir, k: u8, n: u8, args: &[&str]) -> String {
+    let output_path = tmp.path().to_string_lossy().as_ref().to_string();
+
+    let split_out = Command::new("target/debug/rustysecrets")
+        .arg("split")
+        .arg("tests/resources/secret.txt")
+        .args(args)
+        .args(&["-o", &output_path])
+        .args(&["-k", &k.to_string()])
+        .args(&["-n", &n.to_string()])
+        .output()
+        .unwrap();
+
+    assert!(split_out.status.success());
+
+    output_path
+}